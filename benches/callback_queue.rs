@@ -0,0 +1,50 @@
+use bevy::prelude::App;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use bevy_tokio_tasks::{CallbackQueueKind, TokioTasksPlugin, TokioTasksRuntime};
+
+const PRODUCERS: usize = 8;
+const CALLBACKS_PER_PRODUCER: usize = 1000;
+
+/// Several tasks flood the queue with callbacks at once, then a single tick drains them all.
+fn flood_and_drain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("callback_queue");
+    for (name, callback_queue) in [
+        ("inspectable", CallbackQueueKind::Inspectable),
+        ("unbounded", CallbackQueueKind::Unbounded),
+        (
+            "bounded",
+            CallbackQueueKind::Bounded(PRODUCERS * CALLBACKS_PER_PRODUCER),
+        ),
+    ] {
+        let mut app = App::new();
+        app.add_plugins(TokioTasksPlugin {
+            callback_queue,
+            ..TokioTasksPlugin::default()
+        });
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let runtime = app.world().resource::<TokioTasksRuntime>();
+                let producers: Vec<_> = (0..PRODUCERS)
+                    .map(|_| {
+                        runtime.spawn_background_task(|ctx| async move {
+                            for _ in 0..CALLBACKS_PER_PRODUCER {
+                                drop(ctx.enqueue_main_thread(|_ctx| ()));
+                            }
+                        })
+                    })
+                    .collect();
+                runtime.runtime().block_on(async {
+                    for producer in producers {
+                        producer.await.unwrap();
+                    }
+                });
+                app.update();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, flood_and_drain);
+criterion_main!(benches);
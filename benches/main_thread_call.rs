@@ -0,0 +1,64 @@
+use bevy::prelude::App;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use bevy_tokio_tasks::{TokioTasksPlugin, TokioTasksRuntime};
+
+const CALLS: usize = 1000;
+
+/// Builds an app with a current-thread runtime, so that each update drives the task forward to
+/// its next call deterministically.
+fn current_thread_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(TokioTasksPlugin {
+        make_runtime: Box::new(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .expect("Failed to create Tokio runtime")
+        }),
+        ..TokioTasksPlugin::default()
+    });
+    app
+}
+
+/// Makes many round trips to the main thread from a single task, one per update, either with
+/// plain `run_on_main_thread` calls or through an invoker which reuses its return slot.
+fn round_trips(c: &mut Criterion) {
+    let mut group = c.benchmark_group("main_thread_round_trips");
+    let mut app = current_thread_app();
+    group.bench_function("run_on_main_thread", |b| {
+        b.iter(|| {
+            let handle = app
+                .world()
+                .resource::<TokioTasksRuntime>()
+                .spawn_background_task(|mut ctx| async move {
+                    for call in 0..CALLS {
+                        ctx.run_on_main_thread(move |_ctx| call).await;
+                    }
+                });
+            while !handle.is_finished() {
+                app.update();
+            }
+        })
+    });
+    let mut app = current_thread_app();
+    group.bench_function("invoker", |b| {
+        b.iter(|| {
+            let handle = app
+                .world()
+                .resource::<TokioTasksRuntime>()
+                .spawn_background_task(|ctx| async move {
+                    let mut invoker = ctx.main_thread_invoker();
+                    for call in 0..CALLS {
+                        invoker.call(move |_ctx| call).await;
+                    }
+                });
+            while !handle.is_finished() {
+                app.update();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, round_trips);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+use bevy::prelude::App;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use bevy_tokio_tasks::{TokioTasksPlugin, TokioTasksRuntime};
+
+const TASKS: usize = 1000;
+const CHILDREN: usize = 100_000;
+
+/// Spawns many tiny tasks one at a time and as a single batch, then waits for all of them so
+/// that the registry starts each iteration empty.
+fn spawn_many(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn_many");
+    let mut app = App::new();
+    app.add_plugins(TokioTasksPlugin::default());
+    let runtime = app.world().resource::<TokioTasksRuntime>();
+    group.bench_function("individual", |b| {
+        b.iter(|| {
+            let handles: Vec<_> = (0..TASKS)
+                .map(|cell| runtime.spawn_background_task(move |_ctx| async move { cell }))
+                .collect();
+            runtime.runtime().block_on(async {
+                for handle in handles {
+                    handle.await.unwrap();
+                }
+            });
+        })
+    });
+    group.bench_function("batch", |b| {
+        b.iter(|| {
+            let handles = runtime
+                .spawn_background_tasks((0..TASKS).map(|cell| move |_ctx| async move { cell }));
+            runtime.runtime().block_on(async {
+                for handle in handles {
+                    handle.await.unwrap();
+                }
+            });
+        })
+    });
+    group.finish();
+}
+
+/// Spawns many registered children, either from within a single task through a cheap spawner or
+/// from the main thread through the runtime, then waits for all of them.
+fn spawn_children(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn_children");
+    group.sample_size(10);
+    let mut app = App::new();
+    app.add_plugins(TokioTasksPlugin::default());
+    let runtime = app.world().resource::<TokioTasksRuntime>();
+    group.bench_function("cheap_spawner", |b| {
+        b.iter(|| {
+            let parent = runtime.spawn_background_task(|ctx| async move {
+                let spawner = ctx.cheap_spawner();
+                let handles: Vec<_> = (0..CHILDREN)
+                    .map(|child| spawner.spawn(move |_ctx| async move { child }))
+                    .collect();
+                for handle in handles {
+                    handle.await.unwrap();
+                }
+            });
+            runtime.runtime().block_on(parent).unwrap();
+        })
+    });
+    group.bench_function("runtime", |b| {
+        b.iter(|| {
+            let handles: Vec<_> = (0..CHILDREN)
+                .map(|child| runtime.spawn_background_task(move |_ctx| async move { child }))
+                .collect();
+            runtime.runtime().block_on(async {
+                for handle in handles {
+                    handle.await.unwrap();
+                }
+            });
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, spawn_many, spawn_children);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+use bevy::prelude::App;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use bevy_tokio_tasks::{TokioTasksPlugin, TokioTasksRuntime};
+
+fn idle_tick(c: &mut Criterion) {
+    let mut app = App::new();
+    app.add_plugins(TokioTasksPlugin::default());
+    c.bench_function("idle_tick", |b| b.iter(|| app.update()));
+}
+
+fn tick_with_registered_tick_callback(c: &mut Criterion) {
+    let mut app = App::new();
+    app.add_plugins(TokioTasksPlugin::default());
+    // A live world projection keeps a tick callback registered, so every tick has to move the
+    // runtime out of the world to run it.
+    app.world()
+        .resource::<TokioTasksRuntime>()
+        .spawn_background_task(|mut ctx| async move {
+            let _projection = ctx.world_projection(|_world| ());
+            std::future::pending::<()>().await;
+        });
+    c.bench_function("tick_with_registered_tick_callback", |b| {
+        b.iter(|| app.update())
+    });
+}
+
+criterion_group!(benches, idle_tick, tick_with_registered_tick_callback);
+criterion_main!(benches);
@@ -0,0 +1,128 @@
+use std::future::Future;
+use std::sync::atomic::Ordering;
+
+use tokio::task::JoinHandle;
+
+use crate::{TaskContext, TokioTasksRuntime};
+
+/// The load at which
+/// [`try_spawn_background_task`](TokioTasksRuntime::try_spawn_background_task) refuses to admit
+/// new tasks, configured through
+/// [`admission_watermarks`](crate::TokioTasksPlugin::admission_watermarks). A watermark which is
+/// [`None`] is not checked. The default has no watermarks, so every task is admitted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AdmissionWatermarks {
+    /// Refuse new tasks while at least this many one-shot main thread callbacks are queued and
+    /// waiting for the main thread, meaning the main thread is not keeping up with the tasks.
+    pub max_queued_callbacks: Option<usize>,
+    /// Refuse new tasks while at least this many background tasks are running.
+    pub max_live_tasks: Option<usize>,
+}
+
+/// The error returned by
+/// [`try_spawn_background_task`](TokioTasksRuntime::try_spawn_background_task) and
+/// [`check_admission`](TokioTasksRuntime::check_admission) when the runtime is at one of its
+/// [`AdmissionWatermarks`]. It includes the load which was observed at the time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overloaded {
+    /// The number of one-shot main thread callbacks which were queued.
+    pub queued_callbacks: usize,
+    /// The number of background tasks which were running.
+    pub live_tasks: usize,
+}
+
+impl std::fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "background task was not admitted because the runtime is overloaded, with {} queued \
+            main thread callbacks and {} live tasks",
+            self.queued_callbacks, self.live_tasks
+        )
+    }
+}
+
+impl std::error::Error for Overloaded {}
+
+impl TokioTasksRuntime {
+    /// Returns [`Overloaded`] if the runtime is at or above one of the plugin's
+    /// [`AdmissionWatermarks`], in which case
+    /// [`try_spawn_background_task`](Self::try_spawn_background_task) would refuse new tasks.
+    pub fn check_admission(&self) -> Result<(), Overloaded> {
+        let watermarks = self.0.admission_watermarks;
+        let load = Overloaded {
+            queued_callbacks: self
+                .0
+                .shared
+                .main_thread_tx
+                .queued_callbacks
+                .load(Ordering::SeqCst),
+            live_tasks: self.0.shared.registry.live_task_count(),
+        };
+        let at_watermark = |watermark: Option<usize>, value: usize| {
+            watermark.is_some_and(|watermark| value >= watermark)
+        };
+        if at_watermark(watermarks.max_queued_callbacks, load.queued_callbacks)
+            || at_watermark(watermarks.max_live_tasks, load.live_tasks)
+        {
+            return Err(load);
+        }
+        Ok(())
+    }
+
+    /// Spawns a task in the same way as [`spawn_background_task`](Self::spawn_background_task),
+    /// unless the runtime is at one of the plugin's [`AdmissionWatermarks`], in which case the
+    /// task is not spawned and [`Overloaded`] is returned instead. This provides flow control for
+    /// ingestion style workloads, letting producers shed or delay load while the main thread is
+    /// backed up.
+    ///
+    /// The check is made when this is called, so several threads spawning at once can overshoot
+    /// a watermark slightly.
+    #[track_caller]
+    pub fn try_spawn_background_task<Task, Output, Spawnable>(
+        &self,
+        spawnable_task: Spawnable,
+    ) -> Result<JoinHandle<Output>, Overloaded>
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        self.check_admission()?;
+        Ok(self.spawn_background_task(spawnable_task))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing, AdmissionWatermarks, Overloaded, TokioTasksPlugin, TokioTasksRuntime};
+
+    #[test]
+    fn tasks_are_refused_at_the_live_task_watermark() {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            admission_watermarks: AdmissionWatermarks {
+                max_live_tasks: Some(1),
+                ..AdmissionWatermarks::default()
+            },
+            ..testing::plugin()
+        });
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert_eq!(runtime.check_admission(), Ok(()));
+        let first = runtime
+            .try_spawn_background_task(|_ctx| std::future::pending::<()>())
+            .expect("The first task was refused");
+        let load = Overloaded {
+            queued_callbacks: 0,
+            live_tasks: 1,
+        };
+        assert_eq!(runtime.check_admission(), Err(load));
+        let second = runtime.try_spawn_background_task(|_ctx| async {});
+        assert_eq!(second.err(), Some(load));
+
+        first.abort();
+        testing::poll_tasks(&app);
+        app.update();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert_eq!(runtime.check_admission(), Ok(()));
+    }
+}
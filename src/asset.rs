@@ -0,0 +1,178 @@
+use std::panic::Location;
+
+use bevy_asset::{
+    AssetPath, AssetServer, Handle, LoadedUntypedAsset, RecursiveDependencyLoadState,
+    UntypedAssetId,
+};
+
+use crate::{IfAbandoned, MainThreadFailureKind, TaskContext, TickCallback};
+
+/// How far an [`AssetGroupHandle`]'s assets have got, as last observed by the main thread.
+#[derive(Clone, Debug, Default)]
+struct AssetGroupProgress {
+    finished: usize,
+    failed: Vec<AssetPath<'static>>,
+}
+
+/// A group of assets started loading using
+/// [`load_assets_with_progress`](TaskContext::load_assets_with_progress). The load state of each
+/// asset, including its dependencies, is checked on the main thread once per update tick for as
+/// long as this handle is alive and any of the assets are still loading.
+///
+/// The handle holds strong [`Handle`]s to every asset in the group, so the assets stay loaded
+/// until it is dropped or the handles are cloned elsewhere.
+pub struct AssetGroupHandle {
+    handles: Vec<Handle<LoadedUntypedAsset>>,
+    progress_rx: tokio::sync::watch::Receiver<AssetGroupProgress>,
+}
+
+impl AssetGroupHandle {
+    /// The handles of the assets in the group, in the order their paths were given.
+    pub fn handles(&self) -> &[Handle<LoadedUntypedAsset>] {
+        &self.handles
+    }
+
+    /// The fraction of assets in the group which have finished loading, either successfully or
+    /// not, between `0.0` and `1.0`. An empty group reports `1.0`.
+    pub fn progress(&self) -> f32 {
+        if self.handles.is_empty() {
+            return 1.0;
+        }
+        self.progress_rx.borrow().finished as f32 / self.handles.len() as f32
+    }
+
+    /// Waits until every asset in the group has finished loading. Returns an error listing the
+    /// paths of any assets which failed to load, after the rest of the group has finished.
+    pub async fn await_all(&mut self) -> Result<(), AssetGroupFailed> {
+        let total = self.handles.len();
+        let progress = self
+            .progress_rx
+            .wait_for(|progress| progress.finished >= total)
+            .await
+            .expect("Failed to receive asset group progress from main thread");
+        if progress.failed.is_empty() {
+            Ok(())
+        } else {
+            Err(AssetGroupFailed {
+                paths: progress.failed.clone(),
+            })
+        }
+    }
+}
+
+/// The error returned by [`AssetGroupHandle::await_all`] when one or more assets in the group
+/// failed to load.
+#[derive(Clone, Debug)]
+pub struct AssetGroupFailed {
+    /// The paths of the assets which failed to load.
+    pub paths: Vec<AssetPath<'static>>,
+}
+
+impl std::fmt::Display for AssetGroupFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} asset(s) in the group failed to load:",
+            self.paths.len()
+        )?;
+        for path in &self.paths {
+            write!(f, " {path}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AssetGroupFailed {}
+
+impl TaskContext {
+    /// Starts loading a group of assets using the [`AssetServer`] on the main thread, returning
+    /// an [`AssetGroupHandle`] which can report the group's loading progress, such as for a
+    /// loading screen, and wait for the whole group to finish loading.
+    ///
+    /// Panics if the main thread's [`World`](bevy_ecs::world::World) has no [`AssetServer`]
+    /// resource.
+    #[track_caller]
+    pub fn load_assets_with_progress<'a, Paths>(
+        &mut self,
+        paths: Paths,
+    ) -> impl std::future::Future<Output = AssetGroupHandle> + Send + '_
+    where
+        Paths: IntoIterator,
+        Paths::Item: Into<AssetPath<'a>>,
+    {
+        let location = Location::caller();
+        let paths: Vec<AssetPath<'static>> = paths
+            .into_iter()
+            .map(|path| path.into().into_owned())
+            .collect();
+        async move {
+            let handles = self
+                .enqueue_at(
+                    move |ctx| {
+                        let asset_server = ctx
+                            .world
+                            .get_resource::<AssetServer>()
+                            .expect("load_assets_with_progress requires an AssetServer resource");
+                        paths
+                            .into_iter()
+                            .map(|path| (asset_server.load_untyped(path.clone()), path))
+                            .collect::<Vec<_>>()
+                    },
+                    location,
+                    IfAbandoned::Skip,
+                )
+                .await;
+            let mut pending: Vec<(UntypedAssetId, AssetPath<'static>)> = handles
+                .iter()
+                .map(|(handle, path)| (handle.id().untyped(), path.clone()))
+                .collect();
+            let (progress_tx, progress_rx) =
+                tokio::sync::watch::channel(AssetGroupProgress::default());
+            if !pending.is_empty() {
+                let origin = self.callback_origin(location);
+                let callback_origin = origin.clone();
+                let tick_callback: TickCallback = Box::new(move |ctx| {
+                    callback_origin.run(|| {
+                        if progress_tx.is_closed() {
+                            return false;
+                        }
+                        let Some(asset_server) = ctx.world.get_resource::<AssetServer>() else {
+                            return true;
+                        };
+                        let mut failed = Vec::new();
+                        let before = pending.len();
+                        pending.retain(|(id, path)| {
+                            match asset_server.get_recursive_dependency_load_state(*id) {
+                                Some(RecursiveDependencyLoadState::Loaded) => false,
+                                Some(RecursiveDependencyLoadState::Failed(_)) => {
+                                    failed.push(path.clone());
+                                    false
+                                }
+                                _ => true,
+                            }
+                        });
+                        if pending.len() != before {
+                            progress_tx.send_modify(|progress| {
+                                progress.finished += before - pending.len();
+                                progress.failed.extend(failed);
+                            });
+                        }
+                        !pending.is_empty()
+                    })
+                });
+                if self
+                    .shared
+                    .main_thread_tx
+                    .send_tick_callback(tick_callback)
+                    .is_err()
+                {
+                    origin.fail(MainThreadFailureKind::Enqueue);
+                }
+            }
+            AssetGroupHandle {
+                handles: handles.into_iter().map(|(handle, _)| handle).collect(),
+                progress_rx,
+            }
+        }
+    }
+}
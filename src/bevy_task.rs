@@ -0,0 +1,55 @@
+use std::sync::atomic::Ordering;
+
+use crate::TaskContext;
+
+/// The error returned by [`await_bevy_task`](TaskContext::await_bevy_task) when the Bevy task
+/// panicked, or was dropped by its task pool without completing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BevyTaskFailed {
+    /// The message of the panic, if it was raised with a string message.
+    pub message: Option<String>,
+}
+
+impl std::fmt::Display for BevyTaskFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "Bevy task failed: {message}"),
+            None => f.write_str("Bevy task failed"),
+        }
+    }
+}
+
+impl std::error::Error for BevyTaskFailed {}
+
+impl TaskContext {
+    /// Waits for a [`bevy_tasks::Task`], such as one spawned on Bevy's
+    /// [`AsyncComputeTaskPool`](bevy_tasks::AsyncComputeTaskPool), and returns its output. This
+    /// bridges code which already uses Bevy's task pools with background tasks, for example while
+    /// migrating between the two.
+    ///
+    /// The Bevy task wakes this task when it completes, so nothing is polled in the meantime.
+    /// Its output is then returned at the start of the next update tick, so that tasks see Bevy
+    /// task completions in step with the frame, like the rest of their tick based waiting.
+    ///
+    /// Returns [`BevyTaskFailed`] if the Bevy task panicked, or if its task pool dropped it
+    /// without running it to completion, such as while shutting down.
+    pub async fn await_bevy_task<T>(
+        &mut self,
+        task: bevy_tasks::Task<T>,
+    ) -> Result<T, BevyTaskFailed> {
+        // Awaiting a Bevy task which failed panics, which is turned back into an error here.
+        let output = crate::panic::catch_future(task)
+            .await
+            .map_err(|payload| BevyTaskFailed {
+                message: crate::panic::panic_message(payload.as_ref()).map(str::to_owned),
+            });
+        let completed_on_tick = self.shared.ticks.load(Ordering::SeqCst);
+        let ticks = &self.shared.ticks;
+        // An error means the main thread has shut down and no more ticks will occur.
+        let _ = self
+            .update_watch_rx
+            .wait_for(|_| ticks.load(Ordering::SeqCst) != completed_on_tick)
+            .await;
+        output
+    }
+}
@@ -0,0 +1,160 @@
+use bevy_ecs::system::Resource;
+use bevy_utils::{Duration, Instant};
+
+/// A Bevy [`Resource`] limiting how much main thread work [`tick_runtime_update`] does on each
+/// tick. Callbacks which do not fit in a tick's budget stay queued, in order, for the following
+/// ticks. The plugin inserts this with the value of
+/// [`drain_budget`](crate::TokioTasksPlugin::drain_budget), and systems can change it at any time,
+/// for example to lower it during heavy gameplay and raise it during loading screens. The new
+/// budget applies from the next tick onwards.
+///
+/// The budget only applies to one-shot callbacks such as those queued using
+/// [`run_on_main_thread`](crate::TaskContext::run_on_main_thread). Callbacks which run on every
+/// tick, such as [world projections](crate::TaskContext::world_projection), always run.
+///
+/// [`tick_runtime_update`]: crate::tick_runtime_update
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrainBudget {
+    /// The maximum number of callbacks to run per tick, or [`None`] for no limit. A limit of
+    /// zero pauses callbacks entirely until the budget is raised.
+    pub max_callbacks: Option<usize>,
+    /// How long to spend running callbacks per tick, or [`None`] for no limit. The time is
+    /// checked between callbacks, so at least one callback runs on each tick and a single slow
+    /// callback can overrun the budget.
+    pub max_duration: Option<Duration>,
+}
+
+impl DrainBudget {
+    /// A budget with no limits, which runs every queued callback on each tick. This is the
+    /// default.
+    pub const UNLIMITED: Self = Self {
+        max_callbacks: None,
+        max_duration: None,
+    };
+
+    /// Returns whether another callback may run, given how many have already run this tick and
+    /// when the tick's draining started.
+    pub(crate) fn allows(&self, drained: usize, started_at: Instant) -> bool {
+        if self.max_callbacks.is_some_and(|max| drained >= max) {
+            return false;
+        }
+        drained == 0
+            || self
+                .max_duration
+                .is_none_or(|max| started_at.elapsed() < max)
+    }
+}
+
+/// A Bevy [`Resource`] which can hold back all main thread work while letting update ticks keep
+/// advancing, for example to stop callbacks from mutating a half-built world during a loading
+/// screen transition while tasks sleeping with
+/// [`sleep_updates`](crate::TaskContext::sleep_updates) still make progress. The plugin inserts
+/// this unpaused.
+///
+/// While paused, callbacks keep queueing up but none of them run, including per-tick callbacks
+/// such as [world projections](crate::TaskContext::world_projection) and callbacks scheduled for
+/// a specific tick, which run late. Everything that was held back runs on the first tick after
+/// unpausing, subject to the [`DrainBudget`].
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrainControl {
+    /// Whether main thread work is currently held back.
+    pub paused: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::App;
+
+    use crate::{testing, DrainBudget, DrainControl, TaskContext, TokioTasksRuntime};
+
+    fn limited_to(max_callbacks: usize) -> DrainBudget {
+        DrainBudget {
+            max_callbacks: Some(max_callbacks),
+            max_duration: None,
+        }
+    }
+
+    fn queue_callbacks(ctx: &TaskContext, count: usize) {
+        for _ in 0..count {
+            drop(ctx.enqueue_main_thread(|_| {}));
+        }
+    }
+
+    /// Updates the app, and returns how many callbacks ran and how many are still queued.
+    fn update(app: &mut App) -> (usize, usize) {
+        app.update();
+        let health = app.world().resource::<TokioTasksRuntime>().health();
+        (health.callbacks_last_tick, health.queued_callbacks)
+    }
+
+    #[test]
+    fn paused_ticks_use_up_full_drain_requests() {
+        let mut app = testing::app();
+        app.insert_resource(DrainBudget {
+            max_callbacks: Some(1),
+            max_duration: None,
+        });
+        app.insert_resource(DrainControl { paused: true });
+        let handle = testing::spawn(&app, |ctx| async move {
+            for _ in 0..3 {
+                drop(ctx.enqueue_main_thread(|_| {}));
+            }
+            ctx.request_full_drain_next_tick();
+        });
+        testing::poll_tasks(&app);
+        assert!(handle.is_finished());
+        app.update();
+
+        app.insert_resource(DrainControl { paused: false });
+        app.update();
+        let health = app.world().resource::<TokioTasksRuntime>().health();
+        assert_eq!(health.callbacks_last_tick, 1);
+        assert_eq!(health.queued_callbacks, 2);
+    }
+
+    #[test]
+    fn full_drain_requests_only_apply_to_the_next_tick() {
+        let mut app = testing::app();
+        app.insert_resource(limited_to(1));
+        let ctx = testing::context(&mut app);
+        queue_callbacks(&ctx, 4);
+        ctx.request_full_drain_next_tick();
+        assert_eq!(update(&mut app), (4, 0));
+        queue_callbacks(&ctx, 3);
+        assert_eq!(update(&mut app), (1, 2));
+    }
+
+    #[test]
+    fn callback_limit_carries_leftovers_into_later_ticks() {
+        let mut app = testing::app();
+        app.insert_resource(limited_to(2));
+        let ctx = testing::context(&mut app);
+        queue_callbacks(&ctx, 5);
+        assert_eq!(update(&mut app), (2, 3));
+        assert_eq!(update(&mut app), (2, 1));
+        assert_eq!(update(&mut app), (1, 0));
+    }
+
+    #[test]
+    fn changing_the_budget_applies_from_the_next_tick() {
+        let mut app = testing::app();
+        app.insert_resource(limited_to(1));
+        let ctx = testing::context(&mut app);
+        queue_callbacks(&ctx, 5);
+        assert_eq!(update(&mut app), (1, 4));
+        app.insert_resource(DrainBudget::UNLIMITED);
+        assert_eq!(update(&mut app), (4, 0));
+    }
+
+    #[test]
+    fn callback_limit_of_zero_pauses_callbacks() {
+        let mut app = testing::app();
+        app.insert_resource(limited_to(0));
+        let ctx = testing::context(&mut app);
+        queue_callbacks(&ctx, 3);
+        assert_eq!(update(&mut app), (0, 3));
+        assert_eq!(update(&mut app), (0, 3));
+        app.insert_resource(limited_to(2));
+        assert_eq!(update(&mut app), (2, 1));
+    }
+}
@@ -0,0 +1,169 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::Poll;
+
+use tokio::task::JoinHandle;
+
+use crate::{TaskContext, TaskSlot, TokioTasksRuntime};
+
+/// A token which can be used to cooperatively cancel one or more tasks spawned using
+/// [`spawn_cancellable_background_task`](TokioTasksRuntime::spawn_cancellable_background_task).
+/// Clones of a token share the same cancellation state, so cancelling any clone cancels every
+/// task spawned with the token.
+#[derive(Clone, Debug)]
+pub struct CancellationToken(Arc<tokio::sync::watch::Sender<bool>>);
+
+impl CancellationToken {
+    /// Creates a token which has not been cancelled.
+    pub fn new() -> Self {
+        Self(Arc::new(tokio::sync::watch::channel(false).0))
+    }
+
+    /// Cancels the token. Tasks using the token stop at their next await point. Cancelling a
+    /// token more than once has no further effect.
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    /// Returns whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Waits until the token is cancelled.
+    pub async fn cancelled(&self) {
+        let mut cancelled_rx = self.0.subscribe();
+        // The sender is owned by this token, so the channel cannot close while waiting.
+        let _ = cancelled_rx.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The output of a task spawned using
+/// [`spawn_cancellable_background_task`](TokioTasksRuntime::spawn_cancellable_background_task)
+/// which was stopped by its [`CancellationToken`] before it finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("task was cancelled by its cancellation token")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl TaskContext {
+    /// Returns the [`CancellationToken`] which the task was spawned with, if it was spawned using
+    /// [`spawn_cancellable_background_task`](TokioTasksRuntime::spawn_cancellable_background_task).
+    /// Tasks can use this to pass the token on to tasks they spawn themselves, or to check for
+    /// cancellation during long stretches of synchronous work.
+    pub fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+}
+
+impl TaskSlot {
+    /// Spawns a task into this slot in the same way as
+    /// [`spawn_cancellable_background_task`](TokioTasksRuntime::spawn_cancellable_background_task).
+    pub fn start_cancellable<Task, Output, Spawnable>(
+        mut self,
+        token: CancellationToken,
+        spawnable_task: Spawnable,
+    ) -> JoinHandle<Result<Output, Cancelled>>
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        self.context.cancellation_token = Some(token.clone());
+        self.start(move |ctx| {
+            let task = spawnable_task(ctx);
+            async move {
+                let mut cancelled = pin!(token.cancelled());
+                let mut task = pin!(task);
+                std::future::poll_fn(|cx| {
+                    // Check the token first so that a task which is always ready to make progress
+                    // still stops once cancelled.
+                    if cancelled.as_mut().poll(cx).is_ready() {
+                        return Poll::Ready(Err(Cancelled));
+                    }
+                    task.as_mut().poll(cx).map(Ok)
+                })
+                .await
+            }
+        })
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Spawn a task in the same way as [`spawn_background_task`](Self::spawn_background_task),
+    /// but race it against a [`CancellationToken`]. Once the token is cancelled, the task is
+    /// stopped cleanly and its [`JoinHandle`] resolves with [`Cancelled`], without the task having
+    /// to check the token itself.
+    ///
+    /// Cancellation can only take effect at the task's await points, because Rust futures cannot
+    /// be preempted. A task doing a long stretch of synchronous work only stops once that work is
+    /// done and it next awaits something, unless it checks
+    /// [`is_cancelled`](CancellationToken::is_cancelled) itself. Stopping a task drops its future,
+    /// along with anything it owns such as [`Permit`](crate::Permit)s.
+    ///
+    /// If the task is waiting on [`run_on_main_thread`](TaskContext::run_on_main_thread) when it is
+    /// cancelled, the callback is skipped if the main thread has not run it yet. A callback which
+    /// has already run is not undone, and its output is discarded.
+    #[track_caller]
+    pub fn spawn_cancellable_background_task<Task, Output, Spawnable>(
+        &self,
+        token: CancellationToken,
+        spawnable_task: Spawnable,
+    ) -> JoinHandle<Result<Output, Cancelled>>
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        self.reserve_task().start_cancellable(token, spawnable_task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::{CancellationToken, Cancelled};
+    use crate::{testing, TokioTasksRuntime};
+
+    #[test]
+    fn cancelled_tasks_stop_at_their_next_await_point() {
+        let mut app = testing::app();
+        let token = CancellationToken::new();
+        let steps = Arc::new(AtomicUsize::new(0));
+        let task_steps = steps.clone();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let handle =
+            runtime.spawn_cancellable_background_task(token.clone(), |mut ctx| async move {
+                task_steps.fetch_add(1, Ordering::SeqCst);
+                ctx.sleep_updates(2).await;
+                task_steps.fetch_add(1, Ordering::SeqCst);
+            });
+        testing::poll_tasks(&app);
+        assert_eq!(steps.load(Ordering::SeqCst), 1);
+
+        token.cancel();
+        assert_eq!(
+            testing::run_until_finished(&mut app, handle),
+            Err(Cancelled)
+        );
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(steps.load(Ordering::SeqCst), 1);
+    }
+}
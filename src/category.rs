@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::task::JoinHandle;
+
+use crate::{TaskContext, TaskSlot, TokioTasksRuntime};
+
+/// The diagnostics counters of one task category, shared by every task in the category and every
+/// callback those tasks queue.
+pub(crate) struct Category {
+    name: Arc<str>,
+    live_tasks: AtomicUsize,
+    queued_callbacks: AtomicUsize,
+    /// The most recent tick on which a callback from this category ran, and how many ran then.
+    last_run: Mutex<(usize, usize)>,
+}
+
+impl Category {
+    fn new(name: Arc<str>) -> Self {
+        Self {
+            name,
+            live_tasks: AtomicUsize::new(0),
+            queued_callbacks: AtomicUsize::new(0),
+            last_run: Mutex::new((0, 0)),
+        }
+    }
+
+    /// Counts a task as live for as long as the returned guard exists.
+    pub(crate) fn live_task(self: &Arc<Self>) -> CategoryGuard {
+        self.live_tasks.fetch_add(1, Ordering::SeqCst);
+        CategoryGuard {
+            category: self.clone(),
+            counter: |category| &category.live_tasks,
+        }
+    }
+
+    /// Counts a callback as queued for as long as the returned guard exists.
+    pub(crate) fn queued_callback(self: &Arc<Self>) -> CategoryGuard {
+        self.queued_callbacks.fetch_add(1, Ordering::SeqCst);
+        CategoryGuard {
+            category: self.clone(),
+            counter: |category| &category.queued_callbacks,
+        }
+    }
+
+    /// Records that one of the category's callbacks ran on the given tick.
+    pub(crate) fn record_run(&self, current_tick: usize) {
+        let mut last_run = self
+            .last_run
+            .lock()
+            .expect("Category last run mutex was poisoned");
+        if last_run.0 == current_tick {
+            last_run.1 += 1;
+        } else {
+            *last_run = (current_tick, 1);
+        }
+    }
+
+    fn stats(&self, latest_tick: usize) -> CategoryStats {
+        let (last_run_tick, runs) = *self
+            .last_run
+            .lock()
+            .expect("Category last run mutex was poisoned");
+        CategoryStats {
+            live_tasks: self.live_tasks.load(Ordering::SeqCst),
+            queued_callbacks: self.queued_callbacks.load(Ordering::SeqCst),
+            callbacks_last_tick: if last_run_tick == latest_tick {
+                runs
+            } else {
+                0
+            },
+        }
+    }
+}
+
+/// Decrements one of a [`Category`]'s counters when dropped.
+pub(crate) struct CategoryGuard {
+    category: Arc<Category>,
+    counter: fn(&Category) -> &AtomicUsize,
+}
+
+impl Drop for CategoryGuard {
+    fn drop(&mut self) {
+        (self.counter)(&self.category).fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Every category which tasks have been spawned into, keyed by name.
+#[derive(Clone, Default)]
+pub(crate) struct Categories(Arc<Mutex<HashMap<Arc<str>, Arc<Category>>>>);
+
+impl Categories {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Arc<str>, Arc<Category>>> {
+        self.0.lock().expect("Task categories mutex was poisoned")
+    }
+
+    fn get_or_create(&self, name: Arc<str>) -> Arc<Category> {
+        self.lock()
+            .entry(name.clone())
+            .or_insert_with(|| Arc::new(Category::new(name)))
+            .clone()
+    }
+}
+
+/// A snapshot of the activity of one task category, or of several categories added together.
+/// See [`category_stats`](TokioTasksRuntime::category_stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CategoryStats {
+    /// The number of tasks in the category which are running.
+    pub live_tasks: usize,
+    /// The number of main thread callbacks queued by tasks in the category which have not run yet.
+    pub queued_callbacks: usize,
+    /// The number of main thread callbacks queued by tasks in the category which ran during the
+    /// most recent tick.
+    pub callbacks_last_tick: usize,
+}
+
+impl std::ops::Add for CategoryStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            live_tasks: self.live_tasks + other.live_tasks,
+            queued_callbacks: self.queued_callbacks + other.queued_callbacks,
+            callbacks_last_tick: self.callbacks_last_tick + other.callbacks_last_tick,
+        }
+    }
+}
+
+/// The activity of every task category, returned by
+/// [`all_category_stats`](TokioTasksRuntime::all_category_stats).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CategoryBreakdown {
+    /// The stats of each category, sorted by category name.
+    pub categories: Vec<(Arc<str>, CategoryStats)>,
+    /// The stats of all categories added together. Tasks which were not spawned into a category
+    /// are not included.
+    pub total: CategoryStats,
+}
+
+impl TaskContext {
+    /// Returns the name of the category the task was spawned into using
+    /// [`TaskSlot::with_category`], if any.
+    pub fn task_category(&self) -> Option<&str> {
+        self.category.as_ref().map(|category| &*category.name)
+    }
+}
+
+impl TaskSlot {
+    /// Puts the task into a named category, such as `"network"` or `"asset-streaming"`, so that
+    /// its activity counts towards that category's
+    /// [`category_stats`](TokioTasksRuntime::category_stats). This lets a debug overlay break
+    /// down activity by subsystem. Tasks which the task spawns itself are not put into the
+    /// category automatically.
+    pub fn with_category(mut self, category: impl Into<Arc<str>>) -> Self {
+        self.context.category = Some(self.categories.get_or_create(category.into()));
+        self
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Spawn a task in the same way as [`spawn_background_task`](Self::spawn_background_task),
+    /// but put it into a named category. See [`TaskSlot::with_category`].
+    #[track_caller]
+    pub fn spawn_categorized_background_task<Task, Output, Spawnable>(
+        &self,
+        category: impl Into<Arc<str>>,
+        spawnable_task: Spawnable,
+    ) -> JoinHandle<Output>
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        self.reserve_task()
+            .with_category(category)
+            .start(spawnable_task)
+    }
+
+    /// Returns a snapshot of the activity of the named task category, or [`None`] if no task has
+    /// ever been spawned into it.
+    pub fn category_stats(&self, category: &str) -> Option<CategoryStats> {
+        let latest_tick = self.0.shared.ticks.load(Ordering::SeqCst);
+        self.0
+            .categories
+            .lock()
+            .get(category)
+            .map(|category| category.stats(latest_tick))
+    }
+
+    /// Returns a snapshot of the activity of every task category, along with a total.
+    pub fn all_category_stats(&self) -> CategoryBreakdown {
+        let latest_tick = self.0.shared.ticks.load(Ordering::SeqCst);
+        let mut categories: Vec<_> = self
+            .0
+            .categories
+            .lock()
+            .values()
+            .map(|category| (category.name.clone(), category.stats(latest_tick)))
+            .collect();
+        categories.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        let total = categories
+            .iter()
+            .fold(CategoryStats::default(), |total, (_, stats)| total + *stats);
+        CategoryBreakdown { categories, total }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::App;
+
+    use super::CategoryStats;
+    use crate::{testing, TokioTasksRuntime};
+
+    /// Spawns a task into `category` which queues `callbacks` callbacks and then keeps running.
+    fn spawn_queueing_task(app: &App, category: &'static str, callbacks: usize) {
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        runtime.spawn_categorized_background_task(category, move |ctx| async move {
+            for _ in 0..callbacks {
+                drop(ctx.enqueue_main_thread(|_| {}));
+            }
+            std::future::pending::<()>().await;
+        });
+    }
+
+    fn stats(
+        live_tasks: usize,
+        queued_callbacks: usize,
+        callbacks_last_tick: usize,
+    ) -> CategoryStats {
+        CategoryStats {
+            live_tasks,
+            queued_callbacks,
+            callbacks_last_tick,
+        }
+    }
+
+    #[test]
+    fn category_stats_are_tracked_per_category() {
+        let mut app = testing::app();
+        spawn_queueing_task(&app, "network", 2);
+        spawn_queueing_task(&app, "assets", 1);
+        testing::poll_tasks(&app);
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert_eq!(runtime.category_stats("network"), Some(stats(1, 2, 0)));
+        assert_eq!(runtime.category_stats("assets"), Some(stats(1, 1, 0)));
+        assert_eq!(runtime.category_stats("audio"), None);
+
+        app.update();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let breakdown = runtime.all_category_stats();
+        assert_eq!(
+            breakdown.categories,
+            vec![
+                ("assets".into(), stats(1, 0, 1)),
+                ("network".into(), stats(1, 0, 2)),
+            ]
+        );
+        assert_eq!(breakdown.total, stats(2, 0, 3));
+
+        app.update();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert_eq!(runtime.category_stats("network"), Some(stats(1, 0, 0)));
+    }
+}
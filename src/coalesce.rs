@@ -0,0 +1,114 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::Location;
+use std::sync::{Arc, Mutex};
+
+use crate::{IfAbandoned, MainThreadContext, TaskContext};
+
+/// The latest generation queued for each key passed to
+/// [`run_on_main_thread_coalesced`](TaskContext::run_on_main_thread_coalesced).
+#[derive(Clone, Default)]
+pub(crate) struct CoalescedKeys(Arc<Mutex<HashMap<Cow<'static, str>, u64>>>);
+
+impl CoalescedKeys {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Cow<'static, str>, u64>> {
+        self.0.lock().expect("Coalesced keys mutex was poisoned")
+    }
+}
+
+/// The error returned by
+/// [`run_on_main_thread_coalesced`](TaskContext::run_on_main_thread_coalesced) when the callback
+/// was superseded by a newer callback with the same key before the main thread got to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Superseded;
+
+impl std::fmt::Display for Superseded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("main thread callback was superseded by a newer callback with the same key")
+    }
+}
+
+impl std::error::Error for Superseded {}
+
+impl TaskContext {
+    /// Queues a callback to run on the main thread like
+    /// [`run_on_main_thread`](Self::run_on_main_thread), but collapses redundant work: if another
+    /// callback with the same key is queued, from this task or any other, before the main thread
+    /// runs this one, only the most recently queued callback runs.
+    ///
+    /// Only the awaiter of the surviving callback receives its output. The awaiters of callbacks
+    /// which were superseded receive [`Superseded`] once the main thread reaches them, and their
+    /// callbacks never run. The surviving callback runs at the position in the queue of the most
+    /// recent request, not the first. Unlike with [`run_on_main_thread`](Self::run_on_main_thread),
+    /// the surviving callback still runs if its future is dropped before the main thread gets to
+    /// it, since the earlier requests which were collapsed into it would otherwise be lost.
+    #[track_caller]
+    pub fn run_on_main_thread_coalesced<Runnable, Output>(
+        &mut self,
+        key: impl Into<Cow<'static, str>>,
+        runnable: Runnable,
+    ) -> impl Future<Output = Result<Output, Superseded>> + Send + '_
+    where
+        Runnable: FnOnce(MainThreadContext) -> Output + Send + 'static,
+        Output: Send + 'static,
+    {
+        let location = Location::caller();
+        let key = key.into();
+        async move {
+            let coalesced_keys = self.shared.coalesced_keys.clone();
+            let generation = {
+                let mut keys = coalesced_keys.lock();
+                let generation = keys.entry(key.clone()).or_insert(0);
+                *generation = generation.wrapping_add(1);
+                *generation
+            };
+            let callback = move |ctx: MainThreadContext| {
+                {
+                    let mut keys = coalesced_keys.lock();
+                    if keys.get(&key) != Some(&generation) {
+                        return Err(Superseded);
+                    }
+                    keys.remove(&key);
+                }
+                Ok(runnable(ctx))
+            };
+            self.enqueue_at(callback, location, IfAbandoned::Run).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::Resource;
+
+    use crate::{testing, Superseded};
+
+    #[derive(Resource, Default)]
+    struct Runs(usize);
+
+    #[test]
+    fn aborting_the_newest_request_still_runs_the_coalesced_work() {
+        let mut app = testing::app();
+        app.init_resource::<Runs>();
+        let first = testing::spawn(&app, |mut ctx| async move {
+            ctx.run_on_main_thread_coalesced("key", |ctx| ctx.world.resource_mut::<Runs>().0 += 1)
+                .await
+        });
+        testing::poll_tasks(&app);
+        let newest = testing::spawn(&app, |mut ctx| async move {
+            ctx.run_on_main_thread_coalesced("key", |ctx| ctx.world.resource_mut::<Runs>().0 += 1)
+                .await
+        });
+        testing::poll_tasks(&app);
+        newest.abort();
+        testing::poll_tasks(&app);
+
+        assert_eq!(
+            testing::run_until_finished(&mut app, first),
+            Err(Superseded)
+        );
+        app.update();
+        assert_eq!(app.world().resource::<Runs>().0, 1);
+    }
+}
@@ -0,0 +1,91 @@
+use std::future::Future;
+use std::task::Poll;
+
+use bevy_utils::Duration;
+
+use crate::TaskContext;
+
+/// Which of the two waits raced by
+/// [`sleep_ticks_or_duration`](TaskContext::sleep_ticks_or_duration) finished first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WokenBy {
+    /// The requested number of update ticks occurred first.
+    Ticks,
+    /// The requested wall-clock duration elapsed first.
+    Duration,
+}
+
+impl TaskContext {
+    /// Sleeps until either `ticks` main thread updates have occurred, as with
+    /// [`sleep_updates`](Self::sleep_updates), or `duration` of wall-clock time has elapsed, as
+    /// with [`tokio::time::sleep`], whichever comes first, and returns which one it was. This
+    /// suits adaptive timing when the frame rate is variable, such as resuming after two seconds
+    /// or sixty ticks, whichever is sooner.
+    ///
+    /// If both finish at the same time then [`WokenBy::Ticks`] is returned. Once the main thread
+    /// has shut down, no more ticks will occur and this returns [`WokenBy::Ticks`] immediately,
+    /// in the same way that [`sleep_updates`](Self::sleep_updates) returns immediately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime does not have the timer driver enabled, in the same way as
+    /// [`tokio::time::sleep`]. See [`enable_time`](crate::TokioTasksPlugin::enable_time).
+    pub async fn sleep_ticks_or_duration(&mut self, ticks: usize, duration: Duration) -> WokenBy {
+        let mut ticks = std::pin::pin!(self.sleep_updates(ticks));
+        let mut timer = std::pin::pin!(tokio::time::sleep(duration));
+        std::future::poll_fn(|cx| {
+            if ticks.as_mut().poll(cx).is_ready() {
+                Poll::Ready(WokenBy::Ticks)
+            } else if timer.as_mut().poll(cx).is_ready() {
+                Poll::Ready(WokenBy::Duration)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_utils::Duration;
+
+    use super::WokenBy;
+    use crate::testing;
+    #[cfg(feature = "test-util")]
+    use crate::{TokioTasksPlugin, TokioTasksRuntime};
+
+    #[test]
+    fn ticks_which_finish_first_wake_the_task() {
+        let mut app = testing::app();
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            ctx.sleep_ticks_or_duration(2, Duration::from_secs(3600))
+                .await
+        });
+        assert_eq!(
+            testing::run_until_finished(&mut app, handle),
+            WokenBy::Ticks
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn a_duration_which_elapses_first_wakes_the_task() {
+        let mut app = testing::app_with(TokioTasksPlugin::deterministic());
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            ctx.sleep_ticks_or_duration(1000, Duration::from_secs(2))
+                .await
+        });
+        testing::poll_tasks(&app);
+        app.update();
+        app.world()
+            .resource::<TokioTasksRuntime>()
+            .advance_time(Duration::from_secs(2));
+        testing::poll_tasks(&app);
+        assert!(handle.is_finished());
+        assert_eq!(
+            testing::run_until_finished(&mut app, handle),
+            WokenBy::Duration
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use bevy_app::{App, Plugin, Startup, Update};
+use bevy_color::Color;
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::{BuildChildren, ChildBuild, DespawnRecursiveExt};
+use bevy_text::{TextColor, TextFont};
+use bevy_ui::prelude::*;
+use bevy_utils::{Duration, Instant};
+
+use crate::{TaskId, TokioTasksRuntime};
+
+/// A Bevy [`Plugin`] which shows a live "task console" panel in the corner of the screen, listing
+/// every task registered with the [`TokioTasksRuntime`] along with its age and a button to abort
+/// it, a graph of the main thread callback queue depth, the number of callbacks run per frame,
+/// and the most recent task panics. This is intended for development builds, and requires the
+/// `debug-overlay` feature.
+///
+/// The panel is drawn using `bevy_ui`, so the app needs Bevy's `UiPlugin`, which is part of
+/// `DefaultPlugins` when Bevy's `bevy_ui` feature is enabled, and a camera for it to be visible.
+/// It must be added after [`TokioTasksPlugin`](crate::TokioTasksPlugin).
+pub struct TokioTasksDebugPlugin {
+    /// How often the panel's text and task list are rebuilt. The queue depth is still sampled
+    /// every frame. The default value for this field is 250 milliseconds.
+    pub refresh_interval: Duration,
+    /// How many frames of queue depth history the graph shows. The default value for this field
+    /// is 120.
+    pub history_len: usize,
+    /// The maximum number of tasks listed in the panel. The default value for this field is 20.
+    pub max_listed_tasks: usize,
+}
+
+impl Default for TokioTasksDebugPlugin {
+    fn default() -> Self {
+        Self {
+            refresh_interval: Duration::from_millis(250),
+            history_len: 120,
+            max_listed_tasks: 20,
+        }
+    }
+}
+
+impl Plugin for TokioTasksDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DebugPanelSettings {
+            refresh_interval: self.refresh_interval,
+            max_listed_tasks: self.max_listed_tasks,
+        });
+        app.insert_resource(QueueDepthHistory {
+            depths: VecDeque::with_capacity(self.history_len),
+            len: self.history_len,
+        });
+        app.add_systems(Startup, spawn_debug_panel);
+        app.add_systems(
+            Update,
+            (
+                record_queue_depth,
+                abort_pressed_tasks,
+                refresh_debug_panel.after(record_queue_depth),
+            ),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct DebugPanelSettings {
+    refresh_interval: Duration,
+    max_listed_tasks: usize,
+}
+
+/// The number of queued callbacks at the start of each recent frame, oldest first.
+#[derive(Resource)]
+struct QueueDepthHistory {
+    depths: VecDeque<usize>,
+    len: usize,
+}
+
+#[derive(Component)]
+struct DebugSummaryText;
+
+#[derive(Component)]
+struct DebugQueueBar(usize);
+
+#[derive(Component)]
+struct DebugTaskList;
+
+#[derive(Component)]
+struct DebugPanicsText;
+
+#[derive(Component)]
+struct AbortTaskButton(TaskId);
+
+const PANEL_FONT_SIZE: f32 = 12.0;
+const GRAPH_HEIGHT: f32 = 40.0;
+/// How many of each task's most recent captured log records are listed under it.
+#[cfg(feature = "task-logs")]
+const LISTED_TASK_LOGS: usize = 3;
+
+fn panel_text(text: impl Into<String>) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: PANEL_FONT_SIZE,
+            ..Default::default()
+        },
+        TextColor(Color::WHITE),
+    )
+}
+
+fn spawn_debug_panel(mut commands: Commands, history: Res<QueueDepthHistory>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.0),
+                right: Val::Px(8.0),
+                width: Val::Px(320.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..Default::default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            GlobalZIndex(i32::MAX),
+        ))
+        .with_children(|panel| {
+            panel.spawn((panel_text("Tokio tasks"), DebugSummaryText));
+            panel
+                .spawn(Node {
+                    height: Val::Px(GRAPH_HEIGHT),
+                    align_items: AlignItems::FlexEnd,
+                    ..Default::default()
+                })
+                .with_children(|graph| {
+                    for index in 0..history.len {
+                        graph.spawn((
+                            Node {
+                                flex_grow: 1.0,
+                                height: Val::Percent(0.0),
+                                ..Default::default()
+                            },
+                            BackgroundColor(Color::srgb(0.3, 0.7, 1.0)),
+                            DebugQueueBar(index),
+                        ));
+                    }
+                });
+            panel.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(2.0),
+                    ..Default::default()
+                },
+                DebugTaskList,
+            ));
+            panel.spawn((panel_text(""), DebugPanicsText));
+        });
+}
+
+fn record_queue_depth(runtime: Res<TokioTasksRuntime>, mut history: ResMut<QueueDepthHistory>) {
+    if history.len == 0 {
+        return;
+    }
+    if history.depths.len() == history.len {
+        history.depths.pop_front();
+    }
+    let depth = runtime.health().queued_callbacks;
+    history.depths.push_back(depth);
+}
+
+fn abort_pressed_tasks(
+    runtime: Res<TokioTasksRuntime>,
+    buttons: Query<(&Interaction, &AbortTaskButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction == Interaction::Pressed {
+            runtime.abort_task(button.0);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn refresh_debug_panel(
+    mut commands: Commands,
+    runtime: Res<TokioTasksRuntime>,
+    settings: Res<DebugPanelSettings>,
+    history: Res<QueueDepthHistory>,
+    mut last_refresh: Local<Option<Instant>>,
+    mut summary: Query<&mut Text, (With<DebugSummaryText>, Without<DebugPanicsText>)>,
+    mut panics: Query<&mut Text, (With<DebugPanicsText>, Without<DebugSummaryText>)>,
+    mut bars: Query<(&DebugQueueBar, &mut Node)>,
+    task_list: Query<Entity, With<DebugTaskList>>,
+) {
+    if last_refresh.is_some_and(|last_refresh| last_refresh.elapsed() < settings.refresh_interval) {
+        return;
+    }
+    *last_refresh = Some(Instant::now());
+
+    let health = runtime.health();
+    let max_depth = history.depths.iter().copied().max().unwrap_or(0);
+    if let Ok(mut summary) = summary.get_single_mut() {
+        summary.0 = format!(
+            "Tokio tasks: {} live, tick {}\n\
+            Queued callbacks: {} (peak {max_depth}), tick callbacks: {}\n\
+            Callbacks last tick: {}, panicked tasks: {} ({} in the last minute)",
+            health.live_tasks,
+            health.current_tick,
+            health.queued_callbacks,
+            health.tick_callbacks,
+            health.callbacks_last_tick,
+            health.panicked_tasks,
+            health.recent_panics,
+        );
+        if health.draining {
+            summary
+                .0
+                .push_str("\nDraining: no new tasks are being spawned");
+        }
+        if let (Some(average), Some(max)) = (health.spawn_latency.average, health.spawn_latency.max)
+        {
+            let _ = write!(
+                summary.0,
+                "\nSpawn latency: {:.2}ms average, {:.2}ms max",
+                average.as_secs_f64() * 1000.0,
+                max.as_secs_f64() * 1000.0,
+            );
+        }
+        for (name, stats) in runtime.all_category_stats().categories {
+            let _ = write!(
+                summary.0,
+                "\n  {name}: {} live, {} queued, {} last tick",
+                stats.live_tasks, stats.queued_callbacks, stats.callbacks_last_tick,
+            );
+        }
+    }
+
+    // Right-align the history so that the newest sample is always the rightmost bar.
+    let offset = history.len - history.depths.len();
+    for (bar, mut node) in &mut bars {
+        let depth = bar
+            .0
+            .checked_sub(offset)
+            .and_then(|index| history.depths.get(index))
+            .copied()
+            .unwrap_or(0);
+        let percent = if max_depth == 0 {
+            0.0
+        } else {
+            depth as f32 / max_depth as f32 * 100.0
+        };
+        node.height = Val::Percent(percent);
+    }
+
+    if let Ok(task_list) = task_list.get_single() {
+        let tasks = runtime.tasks();
+        let mut list = commands.entity(task_list);
+        list.despawn_descendants();
+        list.with_children(|list| {
+            for task in tasks.iter().take(settings.max_listed_tasks) {
+                let mut label = task.id.to_string();
+                if let Some(name) = &task.name {
+                    let _ = write!(label, " \"{name}\"");
+                }
+                match task.age {
+                    Some(age) => {
+                        let _ = write!(label, " running {:.1}s", age.as_secs_f32());
+                    }
+                    None => label.push_str(" reserved"),
+                }
+                let _ = write!(label, " at {}", task.spawned_at);
+                list.spawn(Node {
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                })
+                .with_children(|row| {
+                    row.spawn(panel_text(label));
+                    if task.age.is_some() {
+                        row.spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::horizontal(Val::Px(4.0)),
+                                ..Default::default()
+                            },
+                            BackgroundColor(Color::srgb(0.6, 0.1, 0.1)),
+                            AbortTaskButton(task.id),
+                        ))
+                        .with_children(|button| {
+                            button.spawn(panel_text("abort"));
+                        });
+                    }
+                });
+                #[cfg(feature = "task-logs")]
+                if let Some(logs) = runtime.task_logs(task.id) {
+                    let recent = logs.len().saturating_sub(LISTED_TASK_LOGS);
+                    for record in &logs[recent..] {
+                        list.spawn(panel_text(format!(
+                            "    {} {}: {}",
+                            record.level, record.target, record.message
+                        )));
+                    }
+                }
+            }
+            if tasks.len() > settings.max_listed_tasks {
+                list.spawn(panel_text(format!(
+                    "... and {} more",
+                    tasks.len() - settings.max_listed_tasks
+                )));
+            }
+        });
+    }
+
+    if let Ok(mut panics) = panics.get_single_mut() {
+        let recent_panics = runtime.recent_panics();
+        let mut text = String::new();
+        if !recent_panics.is_empty() {
+            text.push_str("Recent panics:");
+            for panicked in recent_panics.iter().rev() {
+                let _ = write!(text, "\n  {}", panicked.id);
+                if let Some(name) = &panicked.name {
+                    let _ = write!(text, " \"{name}\"");
+                }
+                let _ = write!(
+                    text,
+                    " {:.0}s ago",
+                    panicked.panicked_at.elapsed().as_secs_f32()
+                );
+            }
+        }
+        panics.0 = text;
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+
+use bevy_utils::Instant;
+
+use crate::registry::TaskRegistry;
+use crate::{HealthSnapshot, TaskInfo, TokioTasksRuntime};
+
+/// A post-mortem snapshot of the [`TokioTasksRuntime`], for including the state of the async
+/// subsystem in crash reports. This is returned by [`dump_state`](TokioTasksRuntime::dump_state)
+/// and [`StateDumper::dump`]. The [`Display`] implementation gives a human readable multi-line
+/// report, and enabling the `serde` feature makes it serializable.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StateDump {
+    /// The runtime's counters and queue depths.
+    pub health: HealthSnapshot,
+    /// The number of tasks which panicked within the last minute, as in
+    /// [`HealthSnapshot::recent_panics`], or [`None`] if another thread was recording a panic at
+    /// the time of the dump. In that case, the count in [`health`](Self::health) is zero.
+    pub recent_panics: Option<usize>,
+    /// Every task which was running or reserved, ordered by id, or [`None`] if the task registry
+    /// was locked at the time of the dump, such as when a task panicked while it was being
+    /// updated.
+    pub tasks: Option<Vec<TaskInfo>>,
+}
+
+impl Display for StateDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let health = &self.health;
+        writeln!(f, "bevy-tokio-tasks state at tick {}:", health.current_tick)?;
+        write!(
+            f,
+            "  live tasks: {}, panicked tasks: {}",
+            health.live_tasks, health.panicked_tasks
+        )?;
+        match self.recent_panics {
+            Some(recent_panics) => writeln!(f, " ({recent_panics} in the last minute)")?,
+            None => writeln!(f)?,
+        }
+        if health.draining {
+            writeln!(f, "  draining: no new tasks are being spawned")?;
+        }
+        writeln!(
+            f,
+            "  queued callbacks: {}, tick callbacks: {}, callbacks last tick: {}",
+            health.queued_callbacks, health.tick_callbacks, health.callbacks_last_tick
+        )?;
+        if let (Some(average), Some(max)) = (health.spawn_latency.average, health.spawn_latency.max)
+        {
+            writeln!(f, "  spawn latency: {average:?} average, {max:?} max")?;
+        }
+        match health.time_since_last_tick {
+            Some(time_since_last_tick) => {
+                writeln!(f, "  last tick: {time_since_last_tick:?} ago")?;
+            }
+            None => writeln!(f, "  last tick: unknown")?,
+        }
+        let Some(tasks) = &self.tasks else {
+            return writeln!(f, "  tasks: unavailable, the task registry was locked");
+        };
+        writeln!(f, "  tasks:")?;
+        for task in tasks {
+            write!(f, "    {}", task.id)?;
+            if let Some(name) = &task.name {
+                write!(f, " \"{name}\"")?;
+            }
+            match task.age {
+                Some(age) => write!(f, " running for {age:?}")?,
+                None => write!(f, " reserved")?,
+            }
+            writeln!(f, ", spawned at {}", task.spawned_at)?;
+        }
+        Ok(())
+    }
+}
+
+/// A cheaply cloneable handle for taking [`StateDump`]s of a [`TokioTasksRuntime`] from outside
+/// the Bevy world, returned by [`state_dumper`](TokioTasksRuntime::state_dumper). It is intended
+/// to be moved into a panic hook, so that a crash report includes what the background tasks
+/// were doing:
+///
+/// ```no_run
+/// # use bevy_app::App;
+/// # use bevy_tokio_tasks::{TokioTasksPlugin, TokioTasksRuntime};
+/// let mut app = App::new();
+/// app.add_plugins(TokioTasksPlugin::default());
+///
+/// let dumper = app.world().resource::<TokioTasksRuntime>().state_dumper();
+/// let default_hook = std::panic::take_hook();
+/// std::panic::set_hook(Box::new(move |info| {
+///     eprintln!("{}", dumper.dump());
+///     default_hook(info);
+/// }));
+/// ```
+///
+/// Dumping never blocks, so it is safe to call while the panicking thread holds one of the
+/// runtime's locks. Any state behind such a lock is left out of the dump instead.
+#[derive(Clone)]
+pub struct StateDumper {
+    registry: Arc<TaskRegistry>,
+    queued_callbacks: Arc<AtomicUsize>,
+    tick_callbacks: Arc<AtomicUsize>,
+    ticks: Arc<AtomicUsize>,
+    last_tick_at: Arc<Mutex<Option<Instant>>>,
+    callbacks_last_tick: Arc<AtomicUsize>,
+}
+
+impl StateDumper {
+    /// Takes a [`StateDump`] of the runtime's current state.
+    pub fn dump(&self) -> StateDump {
+        let last_tick_at = match self.last_tick_at.try_lock() {
+            Ok(last_tick_at) => *last_tick_at,
+            Err(TryLockError::Poisoned(poisoned)) => *poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        let recent_panics = self.registry.try_recent_panic_count();
+        StateDump {
+            health: HealthSnapshot {
+                live_tasks: self.registry.live_task_count(),
+                queued_callbacks: self.queued_callbacks.load(Ordering::SeqCst),
+                tick_callbacks: self.tick_callbacks.load(Ordering::SeqCst),
+                current_tick: self.ticks.load(Ordering::SeqCst),
+                time_since_last_tick: last_tick_at.map(|last_tick_at| last_tick_at.elapsed()),
+                panicked_tasks: self.registry.panicked_task_count(),
+                recent_panics: recent_panics.unwrap_or_default(),
+                draining: self.registry.is_draining(),
+                callbacks_last_tick: self.callbacks_last_tick.load(Ordering::SeqCst),
+                spawn_latency: self.registry.spawn_latency(),
+            },
+            recent_panics,
+            tasks: self.registry.try_task_infos(),
+        }
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Returns a [`StateDumper`] which can take [`StateDump`]s of this runtime from anywhere,
+    /// including panic hooks.
+    pub fn state_dumper(&self) -> StateDumper {
+        let inner = &self.0;
+        StateDumper {
+            registry: inner.shared.registry.clone(),
+            queued_callbacks: inner.shared.main_thread_tx.queued_callbacks.clone(),
+            tick_callbacks: inner.shared.main_thread_tx.tick_callbacks.clone(),
+            ticks: inner.shared.ticks.clone(),
+            last_tick_at: inner.last_tick_at.clone(),
+            callbacks_last_tick: inner.callbacks_last_tick.clone(),
+        }
+    }
+
+    /// Takes a [`StateDump`] listing the live tasks and queue depths, for crash diagnostics. Use
+    /// [`state_dumper`](Self::state_dumper) to take dumps from a panic hook, where the runtime
+    /// resource is not available.
+    pub fn dump_state(&self) -> StateDump {
+        self.state_dumper().dump()
+    }
+}
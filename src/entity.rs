@@ -0,0 +1,324 @@
+use std::future::Future;
+use std::panic::Location;
+
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::{QueryFilter, QueryState};
+use bevy_ecs::system::Commands;
+use bevy_ecs::world::EntityWorldMut;
+
+use crate::{
+    enqueue_callback, CallbackOrigin, IfAbandoned, MainThreadJoin, TaskContext, TaskId,
+    TokioTasksRuntime,
+};
+
+/// A Bevy [`Component`] marking an entity whose task, spawned using
+/// [`spawn_background_task_as_entity`](TokioTasksRuntime::spawn_background_task_as_entity), is
+/// still running. This is removed when the task finishes.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskInProgress(pub TaskId);
+
+/// A Bevy [`Component`] holding the output of a task spawned using
+/// [`spawn_background_task_as_entity`](TokioTasksRuntime::spawn_background_task_as_entity),
+/// inserted on the task's entity once the task completes.
+#[derive(Component, Debug)]
+pub struct TaskOutput<T: Send + Sync + 'static>(pub T);
+
+/// A Bevy [`Component`] inserted instead of [`TaskOutput`] on the entity of a task spawned using
+/// [`spawn_background_task_as_entity`](TokioTasksRuntime::spawn_background_task_as_entity) if the
+/// task panicked or was aborted.
+#[derive(Component, Clone, Debug)]
+pub struct TaskFailed {
+    /// The id of the task which failed.
+    pub task_id: TaskId,
+    /// Whether the task was aborted rather than panicking.
+    pub cancelled: bool,
+    /// The panic message, if the task panicked with a string message.
+    pub message: Option<String>,
+}
+
+impl TokioTasksRuntime {
+    /// Spawn a task in the same way as [`spawn_background_task`](Self::spawn_background_task),
+    /// but represent it as an entity. The entity is spawned immediately with a [`TaskInProgress`]
+    /// component. When the task completes, [`TaskInProgress`] is replaced with a
+    /// [`TaskOutput`] holding the task's output, or with [`TaskFailed`] if the task panicked or
+    /// was aborted. Systems can then find finished tasks by querying for those components.
+    ///
+    /// If the entity is despawned before the task completes, the task's output is discarded.
+    #[track_caller]
+    pub fn spawn_background_task_as_entity<Task, Output, Spawnable>(
+        &self,
+        commands: &mut Commands,
+        spawnable_task: Spawnable,
+    ) -> Entity
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + Sync + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        let slot = self.reserve_task();
+        let task_id = slot.id();
+        let entity = commands.spawn(TaskInProgress(task_id)).id();
+        let origin = CallbackOrigin {
+            formatter: self.0.shared.main_thread_failure_message.clone(),
+            task_id: Some(task_id),
+            task_name: None,
+            category: None,
+            // The task has finished by the time this origin's callback runs, so there is no live
+            // task to attribute its cost to.
+            registry: None,
+            location: Location::caller(),
+            warn_slow_callback: self.0.shared.warn_slow_callback,
+        };
+        let main_thread_tx = self.0.shared.main_thread_tx.clone();
+        let handle = slot.start(spawnable_task);
+        // The watcher is spawned directly onto the runtime rather than as a registered task, so
+        // that it is not counted as a second live task.
+        self.0.runtime.spawn(async move {
+            let result = handle.await;
+            drop(enqueue_callback(
+                &main_thread_tx,
+                origin,
+                IfAbandoned::Run,
+                move |ctx| {
+                    let Ok(mut entity) = ctx.world.get_entity_mut(entity) else {
+                        return;
+                    };
+                    entity.remove::<TaskInProgress>();
+                    match result {
+                        Ok(output) => {
+                            entity.insert(TaskOutput(output));
+                        }
+                        Err(error) => {
+                            let cancelled = error.is_cancelled();
+                            let message = error.try_into_panic().ok().and_then(|payload| {
+                                crate::panic::panic_message(payload.as_ref()).map(str::to_owned)
+                            });
+                            entity.insert(TaskFailed {
+                                task_id,
+                                cancelled,
+                                message,
+                            });
+                        }
+                    }
+                },
+            ));
+        });
+        entity
+    }
+}
+
+impl TaskContext {
+    /// Invokes a synchronous callback on the main Bevy thread in the same way as
+    /// [`run_on_main_thread`](Self::run_on_main_thread), but with mutable access to `entity`
+    /// rather than the whole world. The entity is looked up when the callback runs, and if it has
+    /// been despawned since the task last saw it, the callback is skipped and this resolves with
+    /// [`None`]. This guards against the common race where an entity is despawned while a
+    /// callback which uses it is still queued.
+    #[track_caller]
+    pub fn run_on_main_thread_for_entity<Runnable, Output>(
+        &mut self,
+        entity: Entity,
+        runnable: Runnable,
+    ) -> impl Future<Output = Option<Output>> + Send + '_
+    where
+        Runnable: FnOnce(EntityWorldMut) -> Output + Send + 'static,
+        Output: Send + 'static,
+    {
+        let location = Location::caller();
+        async move {
+            self.enqueue_at(
+                move |ctx| ctx.world.get_entity_mut(entity).ok().map(runnable),
+                location,
+                IfAbandoned::Skip,
+            )
+            .await
+        }
+    }
+
+    /// Waits until `entity` exists in the main Bevy [`World`](bevy_ecs::world::World). Existence
+    /// is checked on the main thread once per update tick, starting with the tick after this is
+    /// called, so this is useful for waiting on entities which another system spawns from data
+    /// the task provided.
+    ///
+    /// Resolves with `true` once the entity exists. This waits forever if the entity never
+    /// appears, so it never resolves with `false`. Use
+    /// [`wait_for_entity_timeout`](Self::wait_for_entity_timeout) to give up after a number of
+    /// ticks instead.
+    #[track_caller]
+    pub fn wait_for_entity(&mut self, entity: Entity) -> MainThreadJoin<bool> {
+        self.run_each_tick_until(
+            move |ctx| ctx.world.get_entity(entity).is_ok().then_some(true),
+            Location::caller(),
+        )
+    }
+
+    /// Like [`wait_for_entity`](Self::wait_for_entity), but gives up once `entity` has been
+    /// checked for on `max_updates` update ticks without appearing. Resolves with `true` if the
+    /// entity appeared, and `false` if it timed out.
+    #[track_caller]
+    pub fn wait_for_entity_timeout(
+        &mut self,
+        entity: Entity,
+        max_updates: usize,
+    ) -> MainThreadJoin<bool> {
+        let mut checks = 0;
+        self.run_each_tick_until(
+            move |ctx| {
+                if ctx.world.get_entity(entity).is_ok() {
+                    return Some(true);
+                }
+                checks += 1;
+                (checks >= max_updates).then_some(false)
+            },
+            Location::caller(),
+        )
+    }
+
+    /// Waits until some entity in the main Bevy [`World`](bevy_ecs::world::World) matches the
+    /// query filter `F`, such as `With<Player>` or `(With<Enemy>, Without<Dead>)`, and resolves
+    /// with that entity. If several entities match on the same tick, an arbitrary one of them is
+    /// returned. Like [`wait_for_entity`](Self::wait_for_entity), the check runs once per update
+    /// tick.
+    #[track_caller]
+    pub fn wait_for_entity_with<F>(&mut self) -> MainThreadJoin<Entity>
+    where
+        F: QueryFilter + 'static,
+    {
+        let mut query: Option<QueryState<Entity, F>> = None;
+        self.run_each_tick_until(
+            move |ctx| {
+                query
+                    .get_or_insert_with(|| QueryState::new(ctx.world))
+                    .iter(ctx.world)
+                    .next()
+            },
+            Location::caller(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+
+    use bevy_app::App;
+    use bevy_ecs::component::Component;
+    use bevy_ecs::entity::Entity;
+    use bevy_ecs::world::Mut;
+
+    use crate::{testing, TaskFailed, TaskInProgress, TaskOutput, TokioTasksRuntime};
+
+    /// An entity which does not exist in a new app until it is spawned by the test.
+    fn unspawned_entity() -> Entity {
+        Entity::from_raw(1000)
+    }
+
+    #[test]
+    fn wait_for_entity_resolves_once_the_entity_exists() {
+        let mut app = testing::app();
+        let entity = unspawned_entity();
+        let handle = testing::spawn(&app, move |mut ctx| async move {
+            ctx.wait_for_entity(entity).await
+        });
+        for _ in 0..3 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        assert!(!handle.is_finished());
+        app.world_mut()
+            .insert_or_spawn_batch([(entity, ())])
+            .expect("Failed to spawn the awaited entity");
+        assert!(testing::run_until_finished(&mut app, handle));
+    }
+
+    #[test]
+    fn wait_for_entity_timeout_gives_up_when_the_entity_never_exists() {
+        let mut app = testing::app();
+        let entity = unspawned_entity();
+        let handle = testing::spawn(&app, move |mut ctx| async move {
+            ctx.wait_for_entity_timeout(entity, 3).await
+        });
+        assert!(!testing::run_until_finished(&mut app, handle));
+    }
+
+    #[derive(Component)]
+    struct Health(u32);
+
+    #[test]
+    fn entity_callbacks_are_skipped_once_the_entity_is_despawned() {
+        let mut app = testing::app();
+        let entity = app.world_mut().spawn(Health(10)).id();
+        let handle = testing::spawn(&app, move |mut ctx| async move {
+            ctx.run_on_main_thread_for_entity(entity, |mut entity| {
+                let mut health = entity.get_mut::<Health>().expect("Entity has no health");
+                health.0 -= 1;
+                health.0
+            })
+            .await
+        });
+        assert_eq!(testing::run_until_finished(&mut app, handle), Some(9));
+
+        let handle = testing::spawn(&app, move |mut ctx| async move {
+            ctx.run_on_main_thread_for_entity(entity, |_| unreachable!())
+                .await
+        });
+        testing::poll_tasks(&app);
+        app.world_mut().despawn(entity);
+        assert_eq!(testing::run_until_finished(&mut app, handle), None::<()>);
+    }
+
+    /// Spawns a task as an entity using the world's own command queue.
+    fn spawn_as_entity<Task>(app: &mut App, task: Task) -> Entity
+    where
+        Task: Future<Output = u32> + Send + 'static,
+    {
+        app.world_mut()
+            .resource_scope(|world, runtime: Mut<TokioTasksRuntime>| {
+                let entity =
+                    runtime.spawn_background_task_as_entity(&mut world.commands(), |_ctx| task);
+                world.flush();
+                entity
+            })
+    }
+
+    #[test]
+    fn task_entities_get_the_output_of_their_task() {
+        let mut app = testing::app();
+        let entity = spawn_as_entity(&mut app, async { 7 });
+        assert!(app.world().get::<TaskInProgress>(entity).is_some());
+        for _ in 0..2 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        let entity = app.world().entity(entity);
+        assert!(!entity.contains::<TaskInProgress>());
+        assert_eq!(
+            entity.get::<TaskOutput<u32>>().map(|output| output.0),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn task_entities_are_marked_cancelled_when_their_task_is_aborted() {
+        let mut app = testing::app();
+        let entity = spawn_as_entity(&mut app, std::future::pending());
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let task_id = app
+            .world()
+            .get::<TaskInProgress>(entity)
+            .expect("Task is not in progress")
+            .0;
+        assert!(runtime.abort_task(task_id));
+        for _ in 0..2 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        let entity = app.world().entity(entity);
+        assert!(!entity.contains::<TaskInProgress>());
+        assert!(!entity.contains::<TaskOutput<u32>>());
+        let failed = entity.get::<TaskFailed>().expect("Task did not fail");
+        assert!(failed.cancelled);
+        assert_eq!(failed.task_id, task_id);
+    }
+}
@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use bevy_utils::{tracing, Duration};
+
+use crate::{default_runtime_builder, TokioTasksPlugin};
+
+/// The number of worker threads, as accepted by
+/// [`Builder::worker_threads`](tokio::runtime::Builder::worker_threads).
+const WORKER_THREADS: &str = "TOKIO_WORKER_THREADS";
+/// The maximum number of blocking threads, as accepted by
+/// [`Builder::max_blocking_threads`](tokio::runtime::Builder::max_blocking_threads).
+const MAX_BLOCKING_THREADS: &str = "TOKIO_MAX_BLOCKING_THREADS";
+/// The stack size of the runtime's threads in bytes, as accepted by
+/// [`Builder::thread_stack_size`](tokio::runtime::Builder::thread_stack_size).
+const THREAD_STACK_SIZE: &str = "TOKIO_THREAD_STACK_SIZE";
+/// The name given to the runtime's threads, as accepted by
+/// [`Builder::thread_name`](tokio::runtime::Builder::thread_name).
+const THREAD_NAME: &str = "TOKIO_THREAD_NAME";
+/// How long idle blocking threads are kept alive in milliseconds, as accepted by
+/// [`Builder::thread_keep_alive`](tokio::runtime::Builder::thread_keep_alive).
+const THREAD_KEEP_ALIVE_MS: &str = "TOKIO_THREAD_KEEP_ALIVE_MS";
+
+/// Runtime builder settings read from environment variables by [`TokioTasksPlugin::from_env`].
+#[derive(Debug, Default)]
+struct EnvRuntimeSettings {
+    worker_threads: Option<usize>,
+    max_blocking_threads: Option<usize>,
+    thread_stack_size: Option<usize>,
+    thread_name: Option<String>,
+    thread_keep_alive: Option<Duration>,
+}
+
+impl EnvRuntimeSettings {
+    fn read(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            // Tokio reads this variable itself when no count is configured, and panics if it is
+            // invalid, so an ignored value falls back to Tokio's usual default explicitly.
+            worker_threads: parse_positive(&lookup, WORKER_THREADS).or_else(|| {
+                lookup(WORKER_THREADS)
+                    .map(|_| std::thread::available_parallelism().map_or(1, |count| count.get()))
+            }),
+            max_blocking_threads: parse_positive(&lookup, MAX_BLOCKING_THREADS),
+            thread_stack_size: parse_positive(&lookup, THREAD_STACK_SIZE),
+            thread_name: lookup(THREAD_NAME).filter(|name| !name.is_empty()),
+            thread_keep_alive: parse(&lookup, THREAD_KEEP_ALIVE_MS).map(Duration::from_millis),
+        }
+    }
+
+    fn apply(&self, builder: &mut tokio::runtime::Builder) {
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        if let Some(max_blocking_threads) = self.max_blocking_threads {
+            builder.max_blocking_threads(max_blocking_threads);
+        }
+        if let Some(thread_stack_size) = self.thread_stack_size {
+            builder.thread_stack_size(thread_stack_size);
+        }
+        if let Some(thread_name) = &self.thread_name {
+            builder.thread_name(thread_name);
+        }
+        if let Some(thread_keep_alive) = self.thread_keep_alive {
+            builder.thread_keep_alive(thread_keep_alive);
+        }
+    }
+}
+
+/// Parses the variable `name`, warning and returning [`None`] if it is set to an invalid value.
+fn parse<T: FromStr>(lookup: &impl Fn(&str) -> Option<String>, name: &str) -> Option<T> {
+    let value = lookup(name)?;
+    let parsed = value.trim().parse().ok();
+    if parsed.is_none() {
+        tracing::warn!("Ignoring the {name} environment variable, {value:?} is not a valid value");
+    }
+    parsed
+}
+
+/// Like [`parse`], but also rejects zero.
+fn parse_positive(lookup: &impl Fn(&str) -> Option<String>, name: &str) -> Option<usize> {
+    let parsed = parse(lookup, name)?;
+    if parsed == 0 {
+        tracing::warn!("Ignoring the {name} environment variable, it must be greater than zero");
+        return None;
+    }
+    Some(parsed)
+}
+
+impl TokioTasksPlugin {
+    /// Configures the plugin with the default runtime, adjusted by the following environment
+    /// variables. This suits server deployments which are configured through their environment.
+    ///
+    /// | Variable | Builder setting |
+    /// |----------|-----------------|
+    /// | `TOKIO_WORKER_THREADS` | [`worker_threads`](tokio::runtime::Builder::worker_threads) |
+    /// | `TOKIO_MAX_BLOCKING_THREADS` | [`max_blocking_threads`](tokio::runtime::Builder::max_blocking_threads) |
+    /// | `TOKIO_THREAD_STACK_SIZE` | [`thread_stack_size`](tokio::runtime::Builder::thread_stack_size), in bytes |
+    /// | `TOKIO_THREAD_NAME` | [`thread_name`](tokio::runtime::Builder::thread_name) |
+    /// | `TOKIO_THREAD_KEEP_ALIVE_MS` | [`thread_keep_alive`](tokio::runtime::Builder::thread_keep_alive), in milliseconds |
+    ///
+    /// Unset variables keep Tokio's defaults. Variables which are set to an invalid value, such
+    /// as a thread count which is not a positive integer, are ignored with a warning. The
+    /// variables are read when the runtime is built, as the plugin is added. Other plugin fields
+    /// which affect the default runtime, such as [`on_thread_start`](Self::on_thread_start), still
+    /// apply.
+    pub fn from_env() -> Self {
+        Self {
+            make_runtime: Box::new(|| {
+                let mut builder = default_runtime_builder();
+                EnvRuntimeSettings::read(|name| std::env::var(name).ok()).apply(&mut builder);
+                builder
+                    .build()
+                    .expect("Failed to create Tokio runtime for background tasks")
+            }),
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_utils::Duration;
+
+    use super::EnvRuntimeSettings;
+
+    fn read(vars: &[(&str, &str)]) -> EnvRuntimeSettings {
+        EnvRuntimeSettings::read(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| (*value).to_owned())
+        })
+    }
+
+    #[test]
+    fn valid_values_are_read() {
+        let settings = read(&[
+            ("TOKIO_WORKER_THREADS", "4"),
+            ("TOKIO_MAX_BLOCKING_THREADS", " 16 "),
+            ("TOKIO_THREAD_STACK_SIZE", "65536"),
+            ("TOKIO_THREAD_NAME", "game-io"),
+            ("TOKIO_THREAD_KEEP_ALIVE_MS", "250"),
+        ]);
+        assert_eq!(settings.worker_threads, Some(4));
+        assert_eq!(settings.max_blocking_threads, Some(16));
+        assert_eq!(settings.thread_stack_size, Some(65536));
+        assert_eq!(settings.thread_name.as_deref(), Some("game-io"));
+        assert_eq!(settings.thread_keep_alive, Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn unset_variables_keep_the_defaults() {
+        let settings = read(&[]);
+        assert_eq!(settings.worker_threads, None);
+        assert_eq!(settings.max_blocking_threads, None);
+        assert_eq!(settings.thread_stack_size, None);
+        assert_eq!(settings.thread_name, None);
+        assert_eq!(settings.thread_keep_alive, None);
+    }
+
+    #[test]
+    fn zero_non_numeric_and_empty_values_are_ignored() {
+        let settings = read(&[
+            ("TOKIO_MAX_BLOCKING_THREADS", "0"),
+            ("TOKIO_THREAD_STACK_SIZE", "large"),
+            ("TOKIO_THREAD_NAME", ""),
+            ("TOKIO_THREAD_KEEP_ALIVE_MS", ""),
+        ]);
+        assert_eq!(settings.max_blocking_threads, None);
+        assert_eq!(settings.thread_stack_size, None);
+        assert_eq!(settings.thread_name, None);
+        assert_eq!(settings.thread_keep_alive, None);
+    }
+
+    #[test]
+    fn invalid_worker_threads_fall_back_to_the_available_parallelism() {
+        let available = std::thread::available_parallelism().map_or(1, |count| count.get());
+        for value in ["0", "many", ""] {
+            let settings = read(&[("TOKIO_WORKER_THREADS", value)]);
+            assert_eq!(settings.worker_threads, Some(available));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+
+use bevy_ecs::event::Event;
+use bevy_utils::tracing;
+use tokio::task::JoinHandle;
+
+use crate::{TaskContext, TaskId, TokioTasksRuntime};
+
+/// A general purpose Bevy [`Event`] describing a failure in a background task, sent using
+/// [`report_task_error`](TaskContext::report_task_error). The plugin registers this event, so
+/// systems can read it with an [`EventReader<TaskError>`](bevy_ecs::event::EventReader).
+#[derive(Event, Clone, Debug)]
+pub struct TaskError {
+    /// The id of the task which reported the error.
+    pub task_id: TaskId,
+    /// The name of the task which reported the error, if it has one.
+    pub task_name: Option<Arc<str>>,
+    /// The stringified error.
+    pub message: String,
+}
+
+/// The Bevy [`Event`] sent when a task spawned using
+/// [`spawn_fallible_task`](TokioTasksRuntime::spawn_fallible_task) finishes with an [`Err`]. The
+/// plugin registers this event, so systems can read it with an
+/// [`EventReader<BackgroundTaskError>`](bevy_ecs::event::EventReader).
+#[derive(Event, Clone, Debug)]
+pub struct BackgroundTaskError {
+    /// The id of the task which failed.
+    pub task_id: TaskId,
+    /// The name of the task which failed, if it has one.
+    pub task_name: Option<Arc<str>>,
+    /// The stringified error which the task finished with.
+    pub message: String,
+}
+
+impl TaskContext {
+    /// Reports an error to the main thread by sending it as a Bevy [`Event`] on the next tick.
+    /// Systems can then handle the errors of fallible background work using an
+    /// [`EventReader<E>`](bevy_ecs::event::EventReader). The event type must have been registered
+    /// using [`App::add_event`](bevy_app::App::add_event), otherwise the error is dropped.
+    ///
+    /// This does not wait for the event to be sent.
+    #[track_caller]
+    pub fn report_error<E: Event>(&self, error: E) {
+        drop(self.enqueue_main_thread(move |ctx| {
+            ctx.world.send_event(error);
+        }));
+    }
+
+    /// Reports an ad-hoc error to the main thread as a [`TaskError`] event tagged with this task's
+    /// id and name. Any error type which implements [`Display`] can be reported this way, such as
+    /// an `anyhow::Error`.
+    #[track_caller]
+    pub fn report_task_error(&self, error: impl Display) {
+        self.report_error(TaskError {
+            task_id: self.task_id,
+            task_name: self.task_name.clone(),
+            message: error.to_string(),
+        });
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Spawn a task with a fallible body, in the same way as
+    /// [`spawn_background_task`](Self::spawn_background_task). If the task finishes with an
+    /// [`Err`], the error is logged along with the task's id and name, and sent to the main thread
+    /// as a [`BackgroundTaskError`] event. This means errors are never lost,
+    /// even when the returned handle is dropped, without matching on the result by hand in every
+    /// task.
+    #[track_caller]
+    pub fn spawn_fallible_task<Task, Error, Spawnable>(
+        &self,
+        spawnable_task: Spawnable,
+    ) -> JoinHandle<Result<(), Error>>
+    where
+        Task: Future<Output = Result<(), Error>> + Send + 'static,
+        Error: Display + Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        self.spawn_background_task(move |ctx| async move {
+            let result = spawnable_task(ctx.clone()).await;
+            if let Err(error) = &result {
+                tracing::error!(
+                    "{}{} failed: {error}",
+                    ctx.task_id,
+                    ctx.task_name
+                        .as_deref()
+                        .map(|name| format!(" \"{name}\""))
+                        .unwrap_or_default(),
+                );
+                ctx.report_error(BackgroundTaskError {
+                    task_id: ctx.task_id,
+                    task_name: ctx.task_name.clone(),
+                    message: error.to_string(),
+                });
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::event::Events;
+
+    use crate::{testing, BackgroundTaskError, TokioTasksRuntime};
+
+    #[test]
+    fn failed_fallible_tasks_send_background_task_errors() {
+        let mut app = testing::app();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let handle = runtime.spawn_fallible_task(|_ctx| async move { Err("broken") });
+        let task_id = runtime.tasks()[0].id;
+        assert!(testing::run_until_finished(&mut app, handle).is_err());
+        app.update();
+
+        let events = app.world().resource::<Events<BackgroundTaskError>>();
+        let errors: Vec<_> = events.iter_current_update_events().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].task_id, task_id);
+        assert_eq!(errors[0].message, "broken");
+    }
+}
@@ -0,0 +1,130 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::panic::Location;
+use std::sync::{Arc, Mutex};
+
+use bevy_ecs::system::Resource;
+use bevy_utils::tracing;
+
+use crate::{IfAbandoned, MainThreadJoin, TaskContext};
+
+type ResourceFold<R> = Box<dyn FnOnce(&mut R) + Send + 'static>;
+
+/// The folds queued by [`fold_resource`](TaskContext::fold_resource) which have not been applied
+/// yet, keyed by the type of the resource they fold into. Each value is a
+/// `Vec<ResourceFold<R>>` for the key's resource type `R`.
+#[derive(Clone, Default)]
+pub(crate) struct ResourceFolds(Arc<Mutex<HashMap<TypeId, Box<dyn Any + Send>>>>);
+
+impl ResourceFolds {
+    fn push<R: Resource>(&self, fold: ResourceFold<R>) {
+        self.lock()
+            .entry(TypeId::of::<R>())
+            .or_insert_with(|| Box::new(Vec::<ResourceFold<R>>::new()))
+            .downcast_mut::<Vec<ResourceFold<R>>>()
+            .expect("Resource folds were registered with mismatched types")
+            .push(fold);
+    }
+
+    fn take<R: Resource>(&self) -> Vec<ResourceFold<R>> {
+        self.lock()
+            .get_mut(&TypeId::of::<R>())
+            .and_then(|folds| folds.downcast_mut::<Vec<ResourceFold<R>>>())
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<TypeId, Box<dyn Any + Send>>> {
+        self.0.lock().expect("Resource folds mutex was poisoned")
+    }
+}
+
+impl TaskContext {
+    /// Queues an update to the resource `R` to be applied on the main thread, batched together
+    /// with every other fold into `R` which is pending at the time. When many tasks update the
+    /// same resource within a tick, the first of their callbacks to run takes every pending fold
+    /// and applies them one after another through a single mutable borrow of the resource, so
+    /// the work of fetching the resource and marking it as changed is done once per batch rather
+    /// than once per update.
+    ///
+    /// Folds into the same resource are always applied in the order that `fold_resource` was
+    /// called, including between different tasks. Because a batch is applied at the queue
+    /// position of its earliest fold, a fold can be applied before other main thread callbacks
+    /// which were queued ahead of it, so code which needs ordering against those callbacks
+    /// should use [`run_on_main_thread`](Self::run_on_main_thread) instead.
+    ///
+    /// The returned [`MainThreadJoin`] resolves once the fold has been applied, and can also be
+    /// dropped, since the fold is applied regardless. If the resource does not exist when the
+    /// batch is applied, the batch is discarded with a warning.
+    #[track_caller]
+    pub fn fold_resource<R, Fold>(&self, fold: Fold) -> MainThreadJoin<()>
+    where
+        R: Resource,
+        Fold: FnOnce(&mut R) + Send + 'static,
+    {
+        let folds = self.shared.resource_folds.clone();
+        folds.push::<R>(Box::new(fold));
+        self.enqueue_at(
+            move |ctx| {
+                // Empty if an earlier callback already applied this fold as part of its batch.
+                let batch = folds.take::<R>();
+                if batch.is_empty() {
+                    return;
+                }
+                let Some(mut resource) = ctx.world.get_resource_mut::<R>() else {
+                    tracing::warn!(
+                        "Discarded {} folds into the resource {} because it does not exist",
+                        batch.len(),
+                        std::any::type_name::<R>(),
+                    );
+                    return;
+                };
+                for fold in batch {
+                    fold(&mut resource);
+                }
+            },
+            Location::caller(),
+            IfAbandoned::Run,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::Last;
+    use bevy_ecs::change_detection::DetectChanges;
+    use bevy_ecs::system::{Res, ResMut, Resource};
+
+    use crate::testing;
+
+    /// The values folded into the resource, in the order they were applied.
+    #[derive(Resource, Default)]
+    struct Log(Vec<u32>);
+
+    /// Whether [`Log`] was changed on each update.
+    #[derive(Resource, Default)]
+    struct Changes(Vec<bool>);
+
+    fn record_changes(log: Res<Log>, mut changes: ResMut<Changes>) {
+        changes.0.push(log.is_changed());
+    }
+
+    #[test]
+    fn folds_from_several_tasks_apply_in_order_as_one_change() {
+        let mut app = testing::app();
+        app.init_resource::<Log>();
+        app.init_resource::<Changes>();
+        app.add_systems(Last, record_changes);
+        let first = testing::context(&mut app);
+        let second = testing::context(&mut app);
+        app.update();
+        drop(first.fold_resource(|log: &mut Log| log.0.push(1)));
+        drop(second.fold_resource(|log: &mut Log| log.0.push(2)));
+        drop(first.fold_resource(|log: &mut Log| log.0.push(3)));
+        app.update();
+        app.update();
+        assert_eq!(app.world().resource::<Log>().0, vec![1, 2, 3]);
+        // The first update is the system's first run, which always sees the resource as changed.
+        assert_eq!(app.world().resource::<Changes>().0, vec![true, true, false]);
+    }
+}
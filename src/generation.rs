@@ -0,0 +1,36 @@
+use std::sync::atomic::Ordering;
+
+use crate::{TaskContext, TokioTasksRuntime};
+
+impl TokioTasksRuntime {
+    /// Marks the main Bevy [`World`](bevy_ecs::world::World) as having been reset, such as when an
+    /// editor swaps in a freshly loaded scene, and returns the new world generation. The plugin
+    /// cannot detect a reset by itself, so apps which replace the world's contents wholesale
+    /// should call this at that point. Long-lived tasks can then notice the change through
+    /// [`world_generation`](TaskContext::world_generation) and re-resolve any
+    /// [`Entity`](bevy_ecs::entity::Entity) ids they hold, which the reset invalidated.
+    pub fn advance_world_generation(&self) -> usize {
+        self.0
+            .shared
+            .world_generation
+            .fetch_add(1, Ordering::SeqCst)
+            .wrapping_add(1)
+    }
+
+    /// Returns the current world generation. See
+    /// [`advance_world_generation`](Self::advance_world_generation).
+    pub fn world_generation(&self) -> usize {
+        self.0.shared.world_generation.load(Ordering::SeqCst)
+    }
+}
+
+impl TaskContext {
+    /// Returns the current world generation, which starts at zero and is incremented every time
+    /// the app calls [`advance_world_generation`](TokioTasksRuntime::advance_world_generation)
+    /// after resetting the world. Tasks which hold on to
+    /// [`Entity`](bevy_ecs::entity::Entity) ids across many ticks can remember the generation
+    /// they resolved them in, and resolve them again whenever it changes.
+    pub fn world_generation(&self) -> usize {
+        self.shared.world_generation.load(Ordering::SeqCst)
+    }
+}
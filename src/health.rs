@@ -0,0 +1,125 @@
+use std::sync::atomic::Ordering;
+
+use bevy_utils::Duration;
+
+use crate::TokioTasksRuntime;
+
+/// A summary of the state of the [`TokioTasksRuntime`], returned by
+/// [`health`](TokioTasksRuntime::health). This is intended for `/healthz` style endpoints and
+/// in-game debug panels. Enable the `serde` feature to make it serializable.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HealthSnapshot {
+    /// The number of background tasks which have been spawned and have not finished yet.
+    pub live_tasks: usize,
+    /// The number of one-shot main thread callbacks, such as those from
+    /// [`run_on_main_thread`](crate::TaskContext::run_on_main_thread), which are queued and
+    /// waiting for the next tick. This is the number of outstanding one-shot calls, with two
+    /// exceptions: callbacks scheduled for a later tick with
+    /// [`run_on_main_thread_at_tick`](crate::TaskContext::run_on_main_thread_at_tick) are not
+    /// counted, and callbacks whose task stopped waiting for them are still counted until the
+    /// next tick skips them.
+    pub queued_callbacks: usize,
+    /// The number of callbacks which run on every tick, such as
+    /// [world projections](crate::TaskContext::world_projection).
+    pub tick_callbacks: usize,
+    /// The current update tick.
+    pub current_tick: usize,
+    /// How long ago the most recent update tick occurred, or [`None`] if no tick has occurred yet.
+    /// A large value means the main thread has stalled or the tick system is not being run.
+    pub time_since_last_tick: Option<Duration>,
+    /// The number of background tasks which have panicked since the runtime was created.
+    pub panicked_tasks: usize,
+    /// The number of background tasks which have panicked within the last minute. Only the 16
+    /// most recent panics are remembered, as listed by
+    /// [`recent_panics`](TokioTasksRuntime::recent_panics), so this is at most 16.
+    pub recent_panics: usize,
+    /// Whether the runtime has started [draining](TokioTasksRuntime::is_draining) for shutdown,
+    /// and so refuses to spawn new tasks.
+    pub draining: bool,
+    /// The number of one-shot main thread callbacks which ran during the most recent tick.
+    pub callbacks_last_tick: usize,
+    /// How long spawned tasks waited before they were first polled.
+    pub spawn_latency: SpawnLatency,
+}
+
+/// How long background tasks have waited between being spawned and their future first being
+/// polled, over every task which has started since the runtime was created. Rising latencies
+/// mean the runtime is saturated and newly spawned tasks are sitting in Tokio's scheduler before
+/// they get to run, which is otherwise invisible. Part of the [`HealthSnapshot`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SpawnLatency {
+    /// The number of tasks which have been polled at least once.
+    pub polled_tasks: u64,
+    /// The average latency, or [`None`] if no task has been polled yet.
+    pub average: Option<Duration>,
+    /// The longest latency, or [`None`] if no task has been polled yet.
+    pub max: Option<Duration>,
+}
+
+impl TokioTasksRuntime {
+    /// Returns a [`HealthSnapshot`] summarizing the current state of the runtime.
+    pub fn health(&self) -> HealthSnapshot {
+        let inner = &self.0;
+        let last_tick_at = *inner
+            .last_tick_at
+            .lock()
+            .expect("Last tick time mutex was poisoned");
+        HealthSnapshot {
+            live_tasks: inner.shared.registry.live_task_count(),
+            queued_callbacks: inner
+                .shared
+                .main_thread_tx
+                .queued_callbacks
+                .load(Ordering::SeqCst),
+            tick_callbacks: inner
+                .shared
+                .main_thread_tx
+                .tick_callbacks
+                .load(Ordering::SeqCst),
+            current_tick: inner.shared.ticks.load(Ordering::SeqCst),
+            time_since_last_tick: last_tick_at.map(|last_tick_at| last_tick_at.elapsed()),
+            panicked_tasks: inner.shared.registry.panicked_task_count(),
+            recent_panics: inner.shared.registry.recent_panic_count(),
+            draining: inner.shared.registry.is_draining(),
+            callbacks_last_tick: inner.callbacks_last_tick.load(Ordering::SeqCst),
+            spawn_latency: inner.shared.registry.spawn_latency(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::AppExit;
+
+    use crate::{testing, TokioTasksPlugin, TokioTasksRuntime};
+
+    #[test]
+    fn health_reports_recent_panics_and_draining() {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            catch_panics: true,
+            ..testing::plugin()
+        });
+        let handle = testing::spawn(&app, |_ctx| async move { panic!("test panic") });
+        testing::poll_tasks(&app);
+        app.update();
+        testing::poll_tasks(&app);
+        assert!(handle.is_finished());
+
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let health = runtime.health();
+        assert_eq!(health.panicked_tasks, 1);
+        assert_eq!(health.recent_panics, 1);
+        assert!(!health.draining);
+
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert!(runtime.health().draining);
+        let dump = runtime.dump_state();
+        assert!(dump.health.draining);
+        assert_eq!(dump.health.recent_panics, 1);
+        assert_eq!(dump.recent_panics, Some(1));
+    }
+}
@@ -0,0 +1,149 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bevy_ecs::event::{Event, EventWriter};
+use bevy_ecs::system::{Local, Res};
+
+use crate::registry::TaskRegistry;
+use crate::TokioTasksRuntime;
+
+/// A Bevy [`Event`] sent when the [`TokioTasksRuntime`] goes from busy to idle, meaning there are
+/// no live background tasks and no queued main thread callbacks. This is useful for showing that
+/// all background work is done, such as hiding a loading spinner. The plugin registers this event.
+///
+/// Idleness is checked once per update tick, just after
+/// [`tick_runtime_update`](crate::tick_runtime_update), so a task which is spawned and finishes
+/// between two checks does not cause an event. No event is sent on startup, only after the
+/// runtime has been seen busy.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RuntimeIdle {
+    /// The update tick on which the runtime was first seen idle.
+    pub tick: usize,
+}
+
+/// The counters needed to tell whether the runtime is idle, cloned out of the runtime so that
+/// [`wait_idle`](TokioTasksRuntime::wait_idle) does not borrow it.
+#[derive(Clone)]
+struct IdleCheck {
+    registry: Arc<TaskRegistry>,
+    queued_callbacks: Arc<AtomicUsize>,
+}
+
+impl IdleCheck {
+    fn is_idle(&self) -> bool {
+        self.registry.live_task_count() == 0 && self.queued_callbacks.load(Ordering::SeqCst) == 0
+    }
+}
+
+impl TokioTasksRuntime {
+    fn idle_check(&self) -> IdleCheck {
+        IdleCheck {
+            registry: self.0.shared.registry.clone(),
+            queued_callbacks: self.0.shared.main_thread_tx.queued_callbacks.clone(),
+        }
+    }
+
+    /// Returns whether there are no live background tasks and no queued main thread callbacks.
+    pub fn is_idle(&self) -> bool {
+        self.idle_check().is_idle()
+    }
+
+    /// Returns a future which resolves once there are no live background tasks and no queued
+    /// main thread callbacks, for example to let a test wait until all the background work it
+    /// started is done. This resolves immediately if the runtime is already idle, and is
+    /// otherwise checked again on every update tick, so the app must keep updating for it to
+    /// resolve. It also resolves if the main thread shuts down.
+    ///
+    /// The future does not borrow the runtime, so it can be awaited while the app updates.
+    pub fn wait_idle(&self) -> impl Future<Output = ()> + Send + 'static {
+        let idle_check = self.idle_check();
+        let mut update_watch_rx = self.0.update_watch_rx.clone();
+        async move {
+            while !idle_check.is_idle() {
+                if update_watch_rx.changed().await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Sends a [`RuntimeIdle`] event on ticks where the runtime is idle after having been busy.
+pub(crate) fn send_runtime_idle(
+    runtime: Option<Res<TokioTasksRuntime>>,
+    mut idle_events: EventWriter<RuntimeIdle>,
+    mut busy: Local<bool>,
+) {
+    let Some(runtime) = runtime else {
+        return;
+    };
+    if !runtime.is_idle() {
+        *busy = true;
+    } else if *busy {
+        *busy = false;
+        idle_events.send(RuntimeIdle {
+            tick: runtime.0.shared.ticks.load(Ordering::SeqCst),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::Last;
+    use bevy_ecs::event::EventReader;
+    use bevy_ecs::system::{ResMut, Resource};
+
+    use crate::{testing, RuntimeIdle, TokioTasksRuntime};
+
+    /// How many [`RuntimeIdle`] events were sent on each update.
+    #[derive(Resource, Default)]
+    struct IdleEvents(Vec<usize>);
+
+    fn record_idle_events(mut events: EventReader<RuntimeIdle>, mut counts: ResMut<IdleEvents>) {
+        counts.0.push(events.read().count());
+    }
+
+    #[test]
+    fn runtime_idle_is_sent_once_when_the_runtime_goes_idle() {
+        let mut app = testing::app();
+        app.init_resource::<IdleEvents>();
+        app.add_systems(Last, record_idle_events);
+        app.update();
+        app.update();
+        let handle = testing::spawn(&app, |_ctx| std::future::pending::<()>());
+        app.update();
+        app.update();
+        handle.abort();
+        app.update();
+        app.update();
+        app.update();
+        assert_eq!(
+            app.world().resource::<IdleEvents>().0,
+            vec![0, 0, 0, 0, 1, 0, 0]
+        );
+    }
+
+    #[test]
+    fn wait_idle_resolves_once_the_last_task_finishes() {
+        let mut app = testing::app();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let idle = runtime.runtime().spawn(runtime.wait_idle());
+        testing::poll_tasks(&app);
+        assert!(idle.is_finished());
+
+        let task = testing::spawn(&app, |_ctx| std::future::pending::<()>());
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let idle = runtime.runtime().spawn(runtime.wait_idle());
+        for _ in 0..3 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        assert!(!idle.is_finished());
+        task.abort();
+        testing::poll_tasks(&app);
+        app.update();
+        testing::poll_tasks(&app);
+        assert!(idle.is_finished());
+    }
+}
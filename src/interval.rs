@@ -0,0 +1,113 @@
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::{MainThreadContext, MainThreadFailureKind, TaskContext, TickCallback};
+
+/// Keeps a periodic main thread callback started by
+/// [`spawn_interval`](TaskContext::spawn_interval) running. The callback stops once this is
+/// dropped or [`stop`](Self::stop) is called.
+#[must_use = "the interval stops as soon as its handle is dropped"]
+pub struct IntervalHandle {
+    stopped: Arc<AtomicBool>,
+}
+
+impl IntervalHandle {
+    /// Stops the callback from running again, the same as dropping the handle.
+    pub fn stop(self) {}
+}
+
+impl Drop for IntervalHandle {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+impl TaskContext {
+    /// Runs `callback` on the main thread every `every_ticks` update ticks, until the returned
+    /// [`IntervalHandle`] is dropped or stopped. The first run happens `every_ticks` ticks after
+    /// this is called. This acts like a temporary Bevy system created from async code, which suits
+    /// short-lived periodic effects such as a damage-over-time ticker, without having to register
+    /// a permanent system and gate it on some state.
+    ///
+    /// The callback keeps running after the task which started it finishes, for as long as the
+    /// handle is kept alive, so the handle can be moved elsewhere, such as into a component.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every_ticks` is zero.
+    #[track_caller]
+    pub fn spawn_interval<Callback>(
+        &self,
+        every_ticks: usize,
+        mut callback: Callback,
+    ) -> IntervalHandle
+    where
+        Callback: FnMut(MainThreadContext) + Send + 'static,
+    {
+        assert!(
+            every_ticks > 0,
+            "spawn_interval requires every_ticks to be greater than zero"
+        );
+        let origin = self.callback_origin(Location::caller());
+        let stopped = Arc::new(AtomicBool::new(false));
+        let callback_stopped = stopped.clone();
+        let callback_origin = origin.clone();
+        let mut ticks_until_run = every_ticks;
+        let tick_callback: TickCallback = Box::new(move |ctx| {
+            if callback_stopped.load(Ordering::SeqCst) {
+                return false;
+            }
+            ticks_until_run -= 1;
+            if ticks_until_run == 0 {
+                ticks_until_run = every_ticks;
+                callback_origin.run(|| callback(ctx));
+            }
+            true
+        });
+        if self
+            .shared
+            .main_thread_tx
+            .send_tick_callback(tick_callback)
+            .is_err()
+        {
+            origin.fail(MainThreadFailureKind::Enqueue);
+        }
+        IntervalHandle { stopped }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::Resource;
+
+    use crate::testing;
+
+    /// The ticks on which the interval callback ran.
+    #[derive(Resource, Default)]
+    struct Runs(Vec<usize>);
+
+    #[test]
+    fn intervals_run_every_few_ticks_until_stopped() {
+        let mut app = testing::app();
+        app.init_resource::<Runs>();
+        let ctx = testing::context(&mut app);
+        let interval = ctx.spawn_interval(2, |ctx| {
+            ctx.world.resource_mut::<Runs>().0.push(ctx.current_tick);
+        });
+        let start = ctx.current_tick();
+        for _ in 0..6 {
+            app.update();
+        }
+        assert_eq!(
+            app.world().resource::<Runs>().0,
+            vec![start + 2, start + 4, start + 6]
+        );
+
+        interval.stop();
+        for _ in 0..4 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<Runs>().0.len(), 3);
+    }
+}
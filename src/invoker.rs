@@ -0,0 +1,224 @@
+use std::future::Future;
+use std::panic::Location;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::sync::Notify;
+
+use crate::{
+    CallbackOrigin, Category, MainThreadCallback, MainThreadContext, MainThreadFailureKind,
+    MainThreadSender, TaskContext,
+};
+
+/// Runs callbacks on the main thread like [`run_on_main_thread`](TaskContext::run_on_main_thread),
+/// but receives their outputs through a single return slot which is reused for every call, rather
+/// than a fresh channel per call. Created by
+/// [`main_thread_invoker`](TaskContext::main_thread_invoker).
+///
+/// Only one call can be in flight per invoker at a time, which [`call`](Self::call) enforces by
+/// borrowing the invoker mutably. Tasks which want several callbacks in flight at once can create
+/// several invokers, or use [`enqueue_main_thread`](TaskContext::enqueue_main_thread).
+pub struct Invoker<Output> {
+    slot: Arc<ReturnSlot<Output>>,
+    main_thread_tx: MainThreadSender,
+    origin: CallbackOrigin,
+}
+
+/// The return slot shared between an [`Invoker`] and the callback of its current call.
+struct ReturnSlot<Output> {
+    state: Mutex<SlotState<Output>>,
+    notify: Notify,
+}
+
+struct SlotState<Output> {
+    /// The number of the call which is waiting for an output, or zero if there is none, such as
+    /// after the waiting call was dropped.
+    waiting_call: u64,
+    next_call: u64,
+    output: Option<Output>,
+    /// Set if the waiting call's callback was dropped by the main thread without being run.
+    dropped: bool,
+}
+
+impl<Output> ReturnSlot<Output> {
+    fn lock(&self) -> MutexGuard<'_, SlotState<Output>> {
+        self.state
+            .lock()
+            .expect("Invoker return slot mutex was poisoned")
+    }
+}
+
+/// Held by a call's callback. Fills the return slot when the callback completes, or flags the
+/// call as failed if the callback is dropped without completing.
+struct Completion<Output> {
+    slot: Arc<ReturnSlot<Output>>,
+    call: u64,
+    /// Set once the call has been completed, so that dropping this does not also mark it as
+    /// failed.
+    completed: bool,
+}
+
+impl<Output> Completion<Output> {
+    fn is_waited_for(&self) -> bool {
+        self.slot.lock().waiting_call == self.call
+    }
+
+    fn complete(mut self, output: Output) {
+        let mut state = self.slot.lock();
+        if state.waiting_call == self.call {
+            state.output = Some(output);
+        }
+        drop(state);
+        self.completed = true;
+        self.slot.notify.notify_one();
+    }
+}
+
+impl<Output> Drop for Completion<Output> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let mut state = self.slot.lock();
+        if state.waiting_call == self.call {
+            state.dropped = true;
+        }
+        drop(state);
+        self.slot.notify.notify_one();
+    }
+}
+
+/// Stops the callback of a call from running if the call is dropped before it completes.
+struct WaitingCall<'a, Output> {
+    slot: &'a ReturnSlot<Output>,
+    call: u64,
+    /// Set once the call has received its output, at which point there is nothing left to stop.
+    finished: bool,
+}
+
+impl<Output> Drop for WaitingCall<'_, Output> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let mut state = self.slot.lock();
+        if state.waiting_call == self.call {
+            state.waiting_call = 0;
+            state.output = None;
+        }
+    }
+}
+
+impl<Output: Send + 'static> Invoker<Output> {
+    /// Invokes a synchronous callback on the main Bevy thread and returns its output, in the same
+    /// way as [`run_on_main_thread`](TaskContext::run_on_main_thread). If the returned future is
+    /// dropped before the main thread gets to the callback, the callback is skipped and never
+    /// runs.
+    ///
+    /// If the callback cannot be run, this panics in the same way as
+    /// [`run_on_main_thread`](TaskContext::run_on_main_thread).
+    #[track_caller]
+    pub fn call<Runnable>(&mut self, runnable: Runnable) -> impl Future<Output = Output> + Send + '_
+    where
+        Runnable: FnOnce(MainThreadContext) -> Output + Send + 'static,
+    {
+        let location = Location::caller();
+        async move {
+            let mut origin = self.origin.clone();
+            origin.location = location;
+            let mut waiting = {
+                let mut state = self.slot.lock();
+                state.next_call += 1;
+                state.waiting_call = state.next_call;
+                state.output = None;
+                state.dropped = false;
+                WaitingCall {
+                    slot: &self.slot,
+                    call: state.next_call,
+                    finished: false,
+                }
+            };
+            let completion = Completion {
+                slot: self.slot.clone(),
+                call: waiting.call,
+                completed: false,
+            };
+            let callback_origin = origin.clone();
+            let category = origin.category.clone();
+            let queued = category.as_ref().map(Category::queued_callback);
+            let callback: MainThreadCallback = Box::new(move |ctx| {
+                drop(queued);
+                if !completion.is_waited_for() {
+                    return;
+                }
+                if let Some(category) = &category {
+                    category.record_run(ctx.current_tick);
+                }
+                completion.complete(callback_origin.run(|| runnable(ctx)));
+            });
+            if let Err(kind) = self.main_thread_tx.send_callback(callback) {
+                origin.fail(kind);
+            }
+            loop {
+                // Created before checking the slot, so that a completion in between is not missed.
+                let notified = self.slot.notify.notified();
+                {
+                    let mut state = self.slot.lock();
+                    if let Some(output) = state.output.take() {
+                        state.waiting_call = 0;
+                        // The call is already marked as finished, so there is nothing for the
+                        // guard to do.
+                        waiting.finished = true;
+                        return output;
+                    }
+                    if state.dropped {
+                        drop(state);
+                        origin.fail(MainThreadFailureKind::ReceiveOutput);
+                    }
+                }
+                notified.await;
+            }
+        }
+    }
+}
+
+impl TaskContext {
+    /// Returns an [`Invoker`] for repeatedly running callbacks on the main thread which all return
+    /// `Output`. In a loop which calls back to the main thread on every iteration, this avoids
+    /// allocating a new return channel for each call.
+    #[track_caller]
+    pub fn main_thread_invoker<Output: Send + 'static>(&self) -> Invoker<Output> {
+        Invoker {
+            slot: Arc::new(ReturnSlot {
+                state: Mutex::new(SlotState {
+                    waiting_call: 0,
+                    next_call: 0,
+                    output: None,
+                    dropped: false,
+                }),
+                notify: Notify::new(),
+            }),
+            main_thread_tx: self.shared.main_thread_tx.clone(),
+            origin: self.callback_origin(Location::caller()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::testing;
+
+    #[test]
+    fn completed_calls_release_the_return_slot() {
+        let mut app = testing::app();
+        let handle = testing::spawn(&app, |ctx| async move {
+            let mut invoker = ctx.main_thread_invoker::<usize>();
+            for call in 0..3 {
+                assert_eq!(invoker.call(move |_| call).await, call);
+            }
+            Arc::strong_count(&invoker.slot)
+        });
+        assert_eq!(testing::run_until_finished(&mut app, handle), 1);
+    }
+}
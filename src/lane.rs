@@ -0,0 +1,228 @@
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use tokio::task::JoinHandle;
+
+use crate::{OrderedLaneInfo, TaskContext, TokioTasksRuntime};
+
+/// The ordered lanes used by [`spawn_ordered_task`](TokioTasksRuntime::spawn_ordered_task), keyed
+/// by name. Each lane is created the first time its name is used.
+#[derive(Default)]
+pub(crate) struct OrderedLanes(Mutex<HashMap<String, Arc<Lane>>>);
+
+impl OrderedLanes {
+    fn get(&self, name: &str) -> Arc<Lane> {
+        let mut lanes = self.0.lock().expect("Ordered lanes mutex was poisoned");
+        if let Some(lane) = lanes.get(name) {
+            return lane.clone();
+        }
+        let lane = Arc::new(Lane::new(name));
+        lanes.insert(name.to_owned(), lane.clone());
+        lane
+    }
+
+    pub(crate) fn infos(&self) -> Vec<OrderedLaneInfo> {
+        let lanes = self.0.lock().expect("Ordered lanes mutex was poisoned");
+        lanes
+            .iter()
+            .map(|(name, lane)| OrderedLaneInfo {
+                name: name.clone(),
+                pending_tasks: lane.turns.pending(),
+            })
+            .collect()
+    }
+}
+
+/// A current-thread runtime running on a dedicated thread, along with the queue of turns which
+/// makes its tasks run one at a time.
+struct Lane {
+    handle: tokio::runtime::Handle,
+    turns: Arc<TurnQueue>,
+    /// Dropped along with the lane, which stops the lane's thread and drops any tasks still on its
+    /// runtime.
+    _shutdown_tx: tokio::sync::oneshot::Sender<()>,
+}
+
+impl Lane {
+    fn new(name: &str) -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to create Tokio runtime for ordered lane");
+        let handle = runtime.handle().clone();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        std::thread::Builder::new()
+            .name(format!("ordered-lane-{name}"))
+            .spawn(move || {
+                runtime.block_on(async move {
+                    let _ = shutdown_rx.await;
+                });
+            })
+            .expect("Failed to spawn ordered lane thread");
+        Self {
+            handle,
+            turns: Arc::new(TurnQueue::default()),
+            _shutdown_tx: shutdown_tx,
+        }
+    }
+}
+
+/// Hands out turns in spawn order, and tracks which turn is allowed to run.
+#[derive(Default)]
+struct TurnQueue {
+    state: Mutex<TurnState>,
+    serving_tx: tokio::sync::watch::Sender<u64>,
+}
+
+#[derive(Default)]
+struct TurnState {
+    next_ticket: u64,
+    serving: u64,
+    /// Turns which have ended but have not been passed by `serving` yet, because their task was
+    /// aborted before its turn came up. They are skipped once `serving` reaches them.
+    ended: BTreeSet<u64>,
+}
+
+impl TurnQueue {
+    fn take_turn(self: &Arc<Self>) -> Turn {
+        let mut state = self.state.lock().expect("Ordered lane mutex was poisoned");
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        Turn {
+            ticket,
+            queue: self.clone(),
+        }
+    }
+
+    /// Returns the number of turns which have been taken and have not ended yet.
+    fn pending(&self) -> usize {
+        let state = self.state.lock().expect("Ordered lane mutex was poisoned");
+        (state.next_ticket - state.serving) as usize - state.ended.len()
+    }
+}
+
+/// A task's place in its lane. The next task's turn comes up when this is dropped, whether the
+/// task finished, panicked, or was aborted.
+struct Turn {
+    ticket: u64,
+    queue: Arc<TurnQueue>,
+}
+
+impl Turn {
+    async fn wait(&self) {
+        let mut serving_rx = self.queue.serving_tx.subscribe();
+        let _ = serving_rx.wait_for(|serving| *serving == self.ticket).await;
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        let mut state = self
+            .queue
+            .state
+            .lock()
+            .expect("Ordered lane mutex was poisoned");
+        state.ended.insert(self.ticket);
+        let mut serving = state.serving;
+        while state.ended.remove(&serving) {
+            serving += 1;
+        }
+        state.serving = serving;
+        self.queue.serving_tx.send_replace(serving);
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Spawns a task onto the ordered lane with the given name. Tasks in the same lane run one at
+    /// a time in the order they were spawned, each starting only once the previous one has
+    /// finished, which suits work that must not interleave, such as sequential writes to a file.
+    /// Tasks in different lanes, and tasks spawned by other means, run independently of each
+    /// other.
+    ///
+    /// Each lane is its own single-threaded Tokio runtime on a dedicated thread, created the first
+    /// time the lane's name is used and shut down along with the [`TokioTasksRuntime`]. Apart from
+    /// running on that thread, lane tasks behave like tasks spawned with
+    /// [`spawn_background_task`](Self::spawn_background_task), so they can still use
+    /// [`run_on_main_thread`](TaskContext::run_on_main_thread). Aborting a task which is waiting
+    /// for its turn removes it from the lane without affecting the order of the others.
+    ///
+    /// Lanes are never removed once created, so every distinct lane name permanently costs a
+    /// thread and a runtime. Lane names should therefore come from a small fixed set, such as one
+    /// per save file slot, rather than being generated per task.
+    ///
+    /// Lanes need threads, so they are not available on the wasm32 architecture.
+    #[track_caller]
+    pub fn spawn_ordered_task<Task, Output, Spawnable>(
+        &self,
+        lane: &str,
+        spawnable_task: Spawnable,
+    ) -> JoinHandle<Output>
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        let lane = self.0.ordered_lanes.get(lane);
+        let turn = lane.turns.take_turn();
+        self.reserve_task()
+            .on_runtime(lane.handle.clone())
+            .start(move |ctx| {
+                let task = spawnable_task(ctx);
+                async move {
+                    turn.wait().await;
+                    task.await
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{testing, TokioTasksRuntime};
+
+    /// Spawns three tasks onto one lane, the first of which waits to be released, and returns
+    /// the order they ran in. The middle task is aborted while waiting for its turn if `abort`
+    /// is set.
+    fn run_lane(abort: bool) -> Vec<usize> {
+        let app = testing::app();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let handles: Vec<_> = (0..3)
+            .zip([Some(release_rx), None, None])
+            .map(|(index, release_rx)| {
+                let ran = ran.clone();
+                runtime.spawn_ordered_task("lane", move |_ctx| async move {
+                    if let Some(release_rx) = release_rx {
+                        let _ = release_rx.await;
+                    }
+                    ran.lock().unwrap().push(index);
+                })
+            })
+            .collect();
+        if abort {
+            handles[1].abort();
+        }
+        release_tx.send(()).unwrap();
+        runtime.runtime().block_on(async {
+            for handle in handles {
+                let _ = handle.await;
+            }
+        });
+        let ran = ran.lock().unwrap().clone();
+        ran
+    }
+
+    #[test]
+    fn lane_tasks_run_in_spawn_order() {
+        assert_eq!(run_lane(false), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn aborting_a_waiting_lane_task_keeps_the_order_of_the_others() {
+        assert_eq!(run_lane(true), vec![0, 2]);
+    }
+}
@@ -1,29 +1,167 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::future::Future;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::ops::ControlFlow;
+use std::panic::Location;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
 
-use bevy_app::{App, Plugin, Update};
-use bevy_ecs::schedule::{InternedScheduleLabel, ScheduleLabel};
+use bevy_app::{App, Last, Plugin, Startup, Update};
+use bevy_ecs::change_detection::DetectChangesMut;
+use bevy_ecs::schedule::{InternedScheduleLabel, IntoSystemConfigs, ScheduleLabel, SystemSet};
+use bevy_ecs::system::{Local, Res};
 use bevy_ecs::{prelude::World, system::Resource};
+use bevy_time::{Real, Time};
+use bevy_utils::{tracing, Duration, Instant};
 
 use tokio::{runtime::Runtime, task::JoinHandle};
 
+mod admission;
+#[cfg(feature = "asset")]
+mod asset;
+mod bevy_task;
+mod budget;
+mod cancel;
+mod category;
+mod coalesce;
+#[cfg(feature = "time")]
+mod deadline;
+#[cfg(feature = "debug-overlay")]
+mod debug;
+mod dump;
+mod entity;
+mod env;
+mod error;
+mod fold;
+mod generation;
+mod health;
+mod idle;
+mod interval;
+mod invoker;
+#[cfg(not(target_arch = "wasm32"))]
+mod lane;
+mod notify;
+mod panic;
+mod param;
+mod pool;
+mod pressure;
+mod progress;
+mod queue;
+mod registries;
+mod registry;
+mod request;
+mod resource;
+mod schedule;
+mod semaphore;
+mod services;
+mod shutdown;
+mod sleep;
+mod startup;
+#[cfg(feature = "task-logs")]
+mod task_log;
+#[cfg(test)]
+mod testing;
+mod tick_stream;
+
+pub use admission::{AdmissionWatermarks, Overloaded};
+#[cfg(feature = "asset")]
+pub use asset::{AssetGroupFailed, AssetGroupHandle};
+pub use bevy_task::BevyTaskFailed;
+pub use budget::{DrainBudget, DrainControl};
+pub use cancel::{CancellationToken, Cancelled};
+use category::{Categories, Category};
+pub use category::{CategoryBreakdown, CategoryStats};
+use coalesce::CoalescedKeys;
+pub use coalesce::Superseded;
+#[cfg(feature = "time")]
+pub use deadline::WokenBy;
+#[cfg(feature = "debug-overlay")]
+pub use debug::TokioTasksDebugPlugin;
+pub use dump::{StateDump, StateDumper};
+pub use entity::{TaskFailed, TaskInProgress, TaskOutput};
+pub use error::{BackgroundTaskError, TaskError};
+use fold::ResourceFolds;
+pub use health::{HealthSnapshot, SpawnLatency};
+pub use idle::RuntimeIdle;
+pub use interval::IntervalHandle;
+pub use invoker::Invoker;
+#[cfg(not(target_arch = "wasm32"))]
+use lane::OrderedLanes;
+use notify::NotifyRegistry;
+pub use pool::TaskExecutor;
+pub use progress::ProgressStream;
+pub use queue::{CallbackQueueKind, DrainOrder};
+use queue::{CallbackQueueSender, MainThreadQueue};
+pub use registries::{
+    NotifyInfo, OrderedLaneInfo, RegistriesSnapshot, RequestChannelInfo, SemaphoreInfo,
+};
+use registry::TaskRegistry;
+pub use registry::{LiveTaskCount, PanickedTask, TaskId, TaskInfo, TaskSlot, TaskSpawner};
+use request::RequestChannels;
+pub use request::RequestReceiver;
+use schedule::{ScheduledCallbackSender, ScheduledCallbacks};
+pub use semaphore::Permit;
+use semaphore::SemaphoreRegistry;
+pub use services::TaskServices;
+use shutdown::{ShutdownCallbacks, ShutdownHooks};
+use sleep::SleepWakeups;
+use startup::StartupTasks;
+pub use startup::TokioTasksAppExt;
+#[cfg(feature = "task-logs")]
+use task_log::LogCapture;
+#[cfg(feature = "task-logs")]
+pub use task_log::{TaskLogLayer, TaskLogRecord};
+pub use tick_stream::{LosslessTick, LosslessTickStream};
+
 /// A re-export of the tokio version used by this crate.
 pub use tokio;
 
+/// Returns a handle to the Tokio runtime which is entered on the current thread, or [`None`] if
+/// there is none, without panicking like [`Handle::current`](tokio::runtime::Handle::current).
+/// Interop code can use this to decide whether it can spawn onto an ambient runtime or needs to
+/// fall back to another mechanism, such as the [`TokioTasksRuntime`] resource.
+///
+/// Inside background tasks spawned by this crate, this returns a handle to the plugin's runtime.
+/// The plugin does not enter its runtime on the main thread, so from Bevy systems this returns
+/// [`None`] unless the app entered a runtime itself, for example with
+/// [`Runtime::enter`] on [`TokioTasksRuntime::runtime`].
+pub fn try_current_handle() -> Option<tokio::runtime::Handle> {
+    tokio::runtime::Handle::try_current().ok()
+}
+
 /// An internal struct keeping track of how many ticks have elapsed since the start of the program.
 #[derive(Resource)]
 struct UpdateTicks {
     ticks: Arc<AtomicUsize>,
+    last_tick_at: Arc<Mutex<Option<Instant>>>,
+    /// The bits of the most recent frame's delta time in seconds, as an [`f32`].
+    frame_delta: Arc<AtomicU32>,
     update_watch_tx: tokio::sync::watch::Sender<()>,
+    /// Set if [`max_sleep_wakeups_per_tick`](TokioTasksPlugin::max_sleep_wakeups_per_tick) is.
+    sleep_wakeups: Option<Arc<SleepWakeups>>,
+    /// Set if [`lossless_tick_capacity`](TokioTasksPlugin::lossless_tick_capacity) is.
+    lossless_tick_tx: Option<tokio::sync::broadcast::Sender<usize>>,
 }
 
 impl UpdateTicks {
     fn increment_ticks(&self) -> usize {
+        *self
+            .last_tick_at
+            .lock()
+            .expect("Last tick time mutex was poisoned") = Some(Instant::now());
         let new_ticks = self.ticks.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
         self.update_watch_tx
             .send(())
             .expect("Failed to send update_watch channel message");
+        if let Some(lossless_tick_tx) = &self.lossless_tick_tx {
+            // An error means no stream is subscribed, so there is no one to deliver the tick to.
+            let _ = lossless_tick_tx.send(new_ticks);
+        }
+        if let Some(sleep_wakeups) = &self.sleep_wakeups {
+            sleep_wakeups.wake_due(new_ticks);
+        }
         new_ticks
     }
 }
@@ -33,73 +171,721 @@ impl UpdateTicks {
 pub struct TokioTasksPlugin {
     /// Callback which is used to create a Tokio runtime when the plugin is installed. The
     /// default value for this field configures a multi-threaded [`Runtime`] with IO and timer
-    /// functionality enabled if building for non-wasm32 architectures. On wasm32 the current-thread
-    /// scheduler is used instead.
+    /// functionality enabled if building for non-wasm32 architectures. On wasm32, or when this
+    /// crate's `rt-multi-thread` feature is disabled, the current-thread scheduler is used
+    /// instead.
     pub make_runtime: Box<dyn Fn() -> Runtime + Send + Sync + 'static>,
     /// The [`ScheduleLabel`] during which the [`tick_runtime_update`] function will be executed.
     /// The default value for this field is [`Update`].
+    ///
+    /// The update tick is incremented once per execution of the schedule, so when this is set to
+    /// [`FixedUpdate`](bevy_app::FixedUpdate) there is exactly one tick per fixed timestep. A frame
+    /// which runs several fixed steps advances the tick several times, and a frame which runs no
+    /// fixed steps does not advance it at all.
     pub schedule_label: InternedScheduleLabel,
+    /// Formats the panic message used when a [`run_on_main_thread`](TaskContext::run_on_main_thread)
+    /// call fails in a way which cannot be recovered from, such as the main thread dropping the
+    /// callback without running it. Applications can override this to add their own context to the
+    /// message. The default value for this field uses the [`Display`](std::fmt::Display)
+    /// implementation of [`MainThreadFailure`].
+    pub main_thread_failure_message: MainThreadFailureFormatter,
+    /// Named semaphores, keyed by name with the number of permits each one starts with. Tasks can
+    /// acquire permits from these using [`acquire_permit`](TaskContext::acquire_permit) to limit
+    /// concurrent access to a shared resource across the whole app. The default value for this
+    /// field is empty.
+    pub semaphores: HashMap<String, usize>,
+    /// Shared service objects, such as database pools and HTTP clients, which tasks can look up
+    /// by type using [`service`](TaskContext::service). The default value for this field is
+    /// empty.
+    pub services: TaskServices,
+    /// A hook which is run on each of the runtime's threads when it starts, which can be used to
+    /// do things like pinning the worker threads to specific CPU cores. This maps to Tokio's
+    /// [`Builder::on_thread_start`](tokio::runtime::Builder::on_thread_start), and is only used by
+    /// the default [`make_runtime`](Self::make_runtime). Custom runtimes should call the builder
+    /// method themselves. The default value for this field is [`None`].
+    ///
+    /// ```ignore
+    /// // Pin each worker thread to one of the cores which Bevy's main loop does not use.
+    /// let cores = Arc::new(Mutex::new(core_affinity::get_core_ids().unwrap().into_iter().skip(2)));
+    /// TokioTasksPlugin {
+    ///     on_thread_start: Some(Arc::new(move || {
+    ///         if let Some(core) = cores.lock().unwrap().next() {
+    ///             core_affinity::set_for_current(core);
+    ///         }
+    ///     })),
+    ///     ..TokioTasksPlugin::default()
+    /// }
+    /// ```
+    pub on_thread_start: Option<ThreadHook>,
+    /// A hook which is run on each of the runtime's threads just before it stops. This maps to
+    /// Tokio's [`Builder::on_thread_stop`](tokio::runtime::Builder::on_thread_stop), and like
+    /// [`on_thread_start`](Self::on_thread_start) is only used by the default
+    /// [`make_runtime`](Self::make_runtime). The default value for this field is [`None`].
+    pub on_thread_stop: Option<ThreadHook>,
+    /// Whether the default [`make_runtime`](Self::make_runtime) enables Tokio's IO driver, which
+    /// is needed for Tokio's networking and other IO types. Apps which only offload CPU work to
+    /// background tasks can turn this off. The default value for this field is `true`.
+    ///
+    /// When both this and [`enable_time`](Self::enable_time) are set, the runtime enables every
+    /// driver that Tokio was compiled with. Enabling only one of the two requires this crate's
+    /// `io` or `time` feature respectively, since the individual drivers can only be selected
+    /// when the matching Tokio feature is enabled.
+    pub enable_io: bool,
+    /// Whether the default [`make_runtime`](Self::make_runtime) enables Tokio's timer driver,
+    /// which is needed for [`tokio::time`] functions such as `sleep`. See
+    /// [`enable_io`](Self::enable_io) for how the two interact. Tick based waiting such as
+    /// [`sleep_updates`](TaskContext::sleep_updates) does not use Tokio's timers, so it keeps
+    /// working with the timer driver disabled. The default value for this field is `true`.
+    pub enable_time: bool,
+    /// The initial value of the [`DrainBudget`] resource, which limits how many main thread
+    /// callbacks run per tick. Systems can change the resource later to adjust the budget while
+    /// the app runs. The default value for this field is [`DrainBudget::UNLIMITED`].
+    pub drain_budget: DrainBudget,
+    /// Selects the queue which carries one-shot callbacks to the main thread, trading throughput
+    /// under heavy load against introspection. The default value for this field is
+    /// [`CallbackQueueKind::Inspectable`].
+    pub callback_queue: CallbackQueueKind,
+    /// The order in which queued callbacks are run each tick. [`DrainOrder::Lifo`] runs the newest
+    /// callbacks first, at the risk of starving older ones, and requires the
+    /// [`CallbackQueueKind::Inspectable`] queue. The default value for this field is
+    /// [`DrainOrder::Fifo`].
+    pub drain_order: DrainOrder,
+    /// Whether to catch panics at the crate's own boundaries and log them as errors instead of
+    /// letting them unwind. When this is set, a task whose future panics is logged, counted in
+    /// [`recent_panics`](TokioTasksRuntime::recent_panics), and then cancelled, so its
+    /// [`JoinHandle`] resolves with a cancelled [`JoinError`](tokio::task::JoinError) rather than
+    /// a panic. A main thread callback which panics is logged and skipped, so the task waiting on
+    /// it fails as if the main thread had dropped the callback, and the panic never unwinds
+    /// through [`tick_runtime_update`] and the rest of the Bevy schedule.
+    ///
+    /// This is intended for targets where unwinding out of the runtime is not acceptable, while
+    /// keeping a `panic = "unwind"` build. It has no effect with `panic = "abort"`, where panics
+    /// cannot be caught at all. The default value for this field is `false`.
+    pub catch_panics: bool,
+    /// If set, a warning is logged whenever a main thread callback takes longer than this to run,
+    /// including the id and name of the task which requested it and where it was requested from.
+    /// This helps track down frame hitches caused by expensive world updates in tasks. Timing
+    /// each callback has a small cost, so this is best left unset in release builds. The default
+    /// value for this field is [`None`].
+    pub warn_slow_callback: Option<Duration>,
+    /// Whether [`sleep_updates`](TaskContext::sleep_updates) counts ticks from the tick which is
+    /// current when it is called, or from the next tick boundary. The default value for this
+    /// field is [`SleepUpdatesFrom::CurrentTick`].
+    pub sleep_updates_from: SleepUpdatesFrom,
+    /// If set, every one-shot main thread callback is passed through this function when it is
+    /// enqueued, and the callback it returns is the one which runs. This allows cross-cutting
+    /// instrumentation such as timing, tracing or panic catching to be applied uniformly to all
+    /// callbacks:
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use bevy_tokio_tasks::{MainThreadCallback, TokioTasksPlugin};
+    /// TokioTasksPlugin {
+    ///     callback_middleware: Some(Arc::new(|callback: MainThreadCallback| {
+    ///         Box::new(move |ctx| {
+    ///             let start = std::time::Instant::now();
+    ///             callback(ctx);
+    ///             println!("Callback took {:?}", start.elapsed());
+    ///         })
+    ///     })),
+    ///     ..TokioTasksPlugin::default()
+    /// }
+    /// # ;
+    /// ```
+    ///
+    /// This covers callbacks from [`run_on_main_thread`](TaskContext::run_on_main_thread) and the
+    /// other methods which run a callback once, including scheduled and must-run callbacks. It
+    /// does not cover per-tick callbacks such as
+    /// [`run_each_tick_until`](TaskContext::run_each_tick_until). The middleware runs on the
+    /// thread which enqueues the callback, while the callback it returns runs on the main thread.
+    /// The default value for this field is [`None`].
+    pub callback_middleware: Option<CallbackMiddleware>,
+    /// The load at which
+    /// [`try_spawn_background_task`](TokioTasksRuntime::try_spawn_background_task) stops
+    /// admitting new tasks. This only affects that method, since
+    /// [`spawn_background_task`](TokioTasksRuntime::spawn_background_task) and the other spawn
+    /// methods always spawn. The default value for this field has no watermarks.
+    pub admission_watermarks: AdmissionWatermarks,
+    /// If set, caps how many tasks sleeping in [`sleep_updates`](TaskContext::sleep_updates) are
+    /// woken per tick. When many tasks sleep until the same tick, waking them all at once gives
+    /// the runtime a burst of work on that tick. With this set, the sleepers wait in a shared
+    /// queue and those over the limit are woken on the following ticks instead, earliest target
+    /// first, which smooths the spike out at the cost of some sleeps running a few ticks long.
+    /// It also means sleeping tasks are only woken when they are due, rather than checking the
+    /// tick counter on every tick.
+    ///
+    /// A limit of zero is treated as one. The default value for this field is [`None`], which
+    /// wakes every due sleeper straight away.
+    pub max_sleep_wakeups_per_tick: Option<usize>,
+    /// The number of ticks which each
+    /// [`lossless_tick_stream`](TaskContext::lossless_tick_stream) buffers for its task. The
+    /// tick waiting helpers only observe the latest tick, so a task which is busy for a few
+    /// frames misses the ticks in between, while a lossless stream yields every tick once. A
+    /// stream which falls further behind than this loses its oldest ticks and reports a
+    /// [`LosslessTick::Lagged`] instead. A capacity of zero is treated as one. The default value
+    /// for this field is [`None`], which disables lossless tick streams.
+    pub lossless_tick_capacity: Option<usize>,
+    /// Where background tasks are polled. Selecting one of Bevy's task pools runs tasks on the
+    /// pool's threads instead of Tokio worker threads, which avoids having both sets of threads
+    /// compete for the CPU, and makes the default [`make_runtime`](Self::make_runtime) use a
+    /// single worker thread which mostly just drives Tokio's timers and IO. See
+    /// [`TaskExecutor`] for the limitations. The default value for this field is
+    /// [`TaskExecutor::Tokio`].
+    pub executor: TaskExecutor,
+    /// The value which the tick counter starts at, for apps which number their ticks from
+    /// somewhere other than zero, such as a replay resuming partway through or a networked client
+    /// syncing to the server's tick. [`current_tick`](TaskContext::current_tick) reports this
+    /// until the first update, and every update adds one to it. The default value for this field
+    /// is `0`.
+    pub initial_tick: usize,
+    /// A hook which [`tick_runtime_update`] calls at the end of every tick, after the tick's main
+    /// thread callbacks have run, with the world and the
+    /// [current tick](TaskContext::current_tick). This suits lightweight logic which is tied to
+    /// the runtime's ticks, such as custom diagnostics, without adding and ordering a separate
+    /// exclusive system. It is called on every tick, including those with nothing to drain or on
+    /// which draining is [paused](DrainControl). The default value for this field is [`None`].
+    pub on_tick: Option<TickHook>,
+    /// The target duration of a frame. While the last completed frame took longer than this,
+    /// [`should_yield`](TaskContext::should_yield) returns `true`, so that tasks doing heavy work
+    /// can back off using [`yield_if_pressured`](TaskContext::yield_if_pressured) while the game
+    /// is struggling. Frame times come from Bevy's [`Time<Real>`] clock. The default value for
+    /// this field is [`None`], which never reports pressure.
+    pub frame_time_budget: Option<Duration>,
+    /// Whether named tasks, such as those spawned with
+    /// [`spawn_named_background_task`](TokioTasksRuntime::spawn_named_background_task), capture
+    /// their tracing events for [`task_logs`](TokioTasksRuntime::task_logs) as if they had been
+    /// started with [`TaskSlot::with_log_capture`]. Events are only captured while a
+    /// [`TaskLogLayer`] is installed. The default value for this field is `false`.
+    #[cfg(feature = "task-logs")]
+    pub capture_named_task_logs: bool,
+}
+
+/// Where [`sleep_updates`](TaskContext::sleep_updates) starts counting from, configured through
+/// [`sleep_updates_from`](TokioTasksPlugin::sleep_updates_from).
+///
+/// The tick counter is incremented at the very start of [`tick_runtime_update`], before any main
+/// thread callbacks run, and sleeping tasks are woken at that point. During the callbacks of tick
+/// `N`, such as just after a task's [`run_on_main_thread`](TaskContext::run_on_main_thread)
+/// returns, [`current_tick`](TaskContext::current_tick) therefore already reports `N`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SleepUpdatesFrom {
+    /// Count from the tick which is current at the time of the call, so `sleep_updates(n)` called
+    /// during or after tick `N` wakes when tick `N + n` starts. The tick in progress is never
+    /// counted as one of the `n`, since it had already started, so a sleep can never find its
+    /// target already passed. This can give less than `n` full ticks between the call and the
+    /// wakeup, though, because the first tick may already be partly over. This is the default.
+    #[default]
+    CurrentTick,
+    /// Start counting from the next tick boundary, so `sleep_updates(n)` called during or after
+    /// tick `N` wakes when tick `N + n + 1` starts. This guarantees that `n` entire ticks run
+    /// between the call and the wakeup.
+    NextTick,
+}
+
+/// A hook which runs on a Tokio runtime thread. See
+/// [`on_thread_start`](TokioTasksPlugin::on_thread_start).
+pub type ThreadHook = Arc<dyn Fn() + Send + Sync + 'static>;
+
+/// A hook which runs on the main thread at the end of every update tick. See
+/// [`on_tick`](TokioTasksPlugin::on_tick).
+pub type TickHook = Arc<dyn Fn(&mut World, usize) + Send + Sync + 'static>;
+
+/// The settings of the plugin which the default [`make_runtime`](TokioTasksPlugin::make_runtime)
+/// applies to the runtime it builds.
+#[derive(Clone)]
+struct DefaultRuntimeSettings {
+    on_thread_start: Option<ThreadHook>,
+    on_thread_stop: Option<ThreadHook>,
+    enable_io: bool,
+    enable_time: bool,
+    /// Only the multi-threaded runtime has worker threads for this to limit.
+    #[cfg_attr(
+        not(all(feature = "rt-multi-thread", not(target_arch = "wasm32"))),
+        allow(dead_code)
+    )]
+    executor: TaskExecutor,
+}
+
+impl Default for DefaultRuntimeSettings {
+    fn default() -> Self {
+        Self {
+            on_thread_start: None,
+            on_thread_stop: None,
+            enable_io: true,
+            enable_time: true,
+            executor: TaskExecutor::Tokio,
+        }
+    }
+}
+
+thread_local! {
+    /// The settings of the plugin which is currently calling its
+    /// [`make_runtime`](TokioTasksPlugin::make_runtime). The callback takes no arguments, so this
+    /// is how the default callback sees the settings of the plugin it belongs to.
+    static DEFAULT_RUNTIME_SETTINGS: RefCell<DefaultRuntimeSettings> =
+        RefCell::new(DefaultRuntimeSettings::default());
+}
+
+/// Builds the runtime used by the default [`make_runtime`](TokioTasksPlugin::make_runtime).
+fn default_runtime() -> Runtime {
+    default_runtime_builder()
+        .build()
+        .expect("Failed to create Tokio runtime for background tasks")
+}
+
+/// Creates the runtime builder used by the default [`make_runtime`](TokioTasksPlugin::make_runtime),
+/// configured with the settings of the plugin being built.
+fn default_runtime_builder() -> tokio::runtime::Builder {
+    #[cfg(all(feature = "rt-multi-thread", not(target_arch = "wasm32")))]
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    #[cfg(not(all(feature = "rt-multi-thread", not(target_arch = "wasm32"))))]
+    let mut runtime = tokio::runtime::Builder::new_current_thread();
+    let settings = DEFAULT_RUNTIME_SETTINGS.with(|settings| settings.borrow().clone());
+    // Tasks run on a Bevy task pool, so the runtime only needs a thread to drive its timers and
+    // IO, and to run the wrappers which wait for the tasks.
+    #[cfg(all(feature = "rt-multi-thread", not(target_arch = "wasm32")))]
+    if settings.executor != TaskExecutor::Tokio {
+        runtime.worker_threads(1);
+    }
+    if settings.enable_io && settings.enable_time {
+        // This enables whichever drivers Tokio was compiled with, without this crate needing to
+        // enable the corresponding Tokio features itself.
+        runtime.enable_all();
+    } else {
+        if settings.enable_io {
+            #[cfg(feature = "io")]
+            runtime.enable_io();
+            #[cfg(not(feature = "io"))]
+            tracing::warn!(
+                "TokioTasksPlugin::enable_io was set without enable_time, which requires the \
+                bevy-tokio-tasks io feature. The IO driver will not be enabled."
+            );
+        }
+        if settings.enable_time {
+            #[cfg(feature = "time")]
+            runtime.enable_time();
+            #[cfg(not(feature = "time"))]
+            tracing::warn!(
+                "TokioTasksPlugin::enable_time was set without enable_io, which requires the \
+                bevy-tokio-tasks time feature. The timer driver will not be enabled."
+            );
+        }
+    }
+    if let Some(on_thread_start) = settings.on_thread_start {
+        runtime.on_thread_start(move || on_thread_start());
+    }
+    if let Some(on_thread_stop) = settings.on_thread_stop {
+        runtime.on_thread_stop(move || on_thread_stop());
+    }
+    runtime
 }
 
 impl Default for TokioTasksPlugin {
     /// Configures the plugin to build a new Tokio [`Runtime`] with both IO and timer functionality
-    /// enabled. On the wasm32 architecture, the [`Runtime`] will be the current-thread runtime, on all other
-    /// architectures the [`Runtime`] will be the multi-thread runtime.
-    /// 
+    /// enabled. On the wasm32 architecture, or when the `rt-multi-thread` feature is disabled, the
+    /// [`Runtime`] will be the current-thread runtime, otherwise it will be the multi-thread
+    /// runtime.
+    ///
     /// The default schedule label is [`Update`].
+    ///
+    /// The default main thread failure message is the [`Display`](std::fmt::Display) output of the
+    /// [`MainThreadFailure`].
     fn default() -> Self {
+        Self {
+            make_runtime: Box::new(default_runtime),
+            schedule_label: Update.intern(),
+            main_thread_failure_message: Arc::new(|failure| failure.to_string()),
+            semaphores: HashMap::new(),
+            services: TaskServices::default(),
+            on_thread_start: None,
+            on_thread_stop: None,
+            enable_io: true,
+            enable_time: true,
+            drain_budget: DrainBudget::UNLIMITED,
+            callback_queue: CallbackQueueKind::default(),
+            drain_order: DrainOrder::Fifo,
+            catch_panics: false,
+            warn_slow_callback: None,
+            sleep_updates_from: SleepUpdatesFrom::CurrentTick,
+            callback_middleware: None,
+            admission_watermarks: AdmissionWatermarks::default(),
+            max_sleep_wakeups_per_tick: None,
+            lossless_tick_capacity: None,
+            executor: TaskExecutor::Tokio,
+            initial_tick: 0,
+            on_tick: None,
+            frame_time_budget: None,
+            #[cfg(feature = "task-logs")]
+            capture_named_task_logs: false,
+        }
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl TokioTasksPlugin {
+    /// Configures the plugin for reproducible tests of concurrent task interactions. The runtime
+    /// is a current-thread [`Runtime`] with Tokio's clock paused, so background tasks only make
+    /// progress while [`tick_runtime_update`] runs and timers only fire when the test advances
+    /// time using [`TokioTasksRuntime::advance_time`]. Main thread callbacks are always run in
+    /// the order they were requested, so two tasks which queue callbacks during the same tick
+    /// observe the same ordering on every run.
+    ///
+    /// Ticks are advanced explicitly by calling [`App::update`].
+    pub fn deterministic() -> Self {
         Self {
             make_runtime: Box::new(|| {
-                #[cfg(not(target_arch = "wasm32"))]
-                let mut runtime = tokio::runtime::Builder::new_multi_thread();
-                #[cfg(target_arch = "wasm32")]
-                let mut runtime = tokio::runtime::Builder::new_current_thread();
-                runtime.enable_all();
-                runtime
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .start_paused(true)
                     .build()
-                    .expect("Failed to create Tokio runtime for background tasks")
+                    .expect("Failed to create deterministic Tokio runtime for background tasks")
             }),
-            schedule_label: Update.intern()
+            ..Self::default()
         }
     }
 }
 
+impl TokioTasksPlugin {
+    /// Calls [`make_runtime`](Self::make_runtime) with this plugin's settings visible to the
+    /// default runtime builder.
+    fn build_runtime(&self) -> Runtime {
+        let settings = DefaultRuntimeSettings {
+            on_thread_start: self.on_thread_start.clone(),
+            on_thread_stop: self.on_thread_stop.clone(),
+            enable_io: self.enable_io,
+            enable_time: self.enable_time,
+            executor: self.executor,
+        };
+        let previous = DEFAULT_RUNTIME_SETTINGS.with(|current| current.replace(settings));
+        let runtime = (self.make_runtime)();
+        DEFAULT_RUNTIME_SETTINGS.with(|current| current.replace(previous));
+        runtime
+    }
+}
+
 impl Plugin for TokioTasksPlugin {
     fn build(&self, app: &mut App) {
-        let ticks = Arc::new(AtomicUsize::new(0));
+        let ticks = Arc::new(AtomicUsize::new(self.initial_tick));
+        let last_tick_at = Arc::new(Mutex::new(None));
+        let frame_delta = Arc::new(AtomicU32::new(0.0f32.to_bits()));
         let (update_watch_tx, update_watch_rx) = tokio::sync::watch::channel(());
-        let runtime = (self.make_runtime)();
+        let runtime = self.build_runtime();
+        let sleep_wakeups = self
+            .max_sleep_wakeups_per_tick
+            .map(|max_per_tick| Arc::new(SleepWakeups::new(max_per_tick)));
+        let sleep_wakeups_ref = sleep_wakeups.as_ref().map(Arc::downgrade);
+        let (lossless_tick_tx, lossless_tick_rx) = self
+            .lossless_tick_capacity
+            .map(|capacity| tokio::sync::broadcast::channel(capacity.max(1)))
+            .unzip();
         app.insert_resource(UpdateTicks {
             ticks: ticks.clone(),
+            last_tick_at: last_tick_at.clone(),
+            frame_delta: frame_delta.clone(),
             update_watch_tx,
+            sleep_wakeups,
+            lossless_tick_tx,
+        });
+        app.insert_resource(TokioTasksRuntime::new(
+            ticks,
+            last_tick_at,
+            frame_delta,
+            runtime,
+            update_watch_rx,
+            sleep_wakeups_ref,
+            lossless_tick_rx,
+            self,
+        ));
+        app.insert_resource(TokioTasksConfig {
+            schedule_label: self.schedule_label,
         });
-        app.insert_resource(TokioTasksRuntime::new(ticks, runtime, update_watch_rx));
-        app.add_systems(self.schedule_label, tick_runtime_update);
+        app.insert_resource(self.drain_budget);
+        app.init_resource::<DrainControl>();
+        app.init_resource::<LiveTaskCount>();
+        app.add_event::<TaskError>();
+        app.add_event::<BackgroundTaskError>();
+        app.add_event::<RuntimeIdle>();
+        app.add_systems(
+            self.schedule_label,
+            (
+                tick_runtime_update.in_set(TokioTasksSystems::TickRuntimeUpdate),
+                idle::send_runtime_idle.after(TokioTasksSystems::TickRuntimeUpdate),
+            ),
+        );
+        app.add_systems(
+            Last,
+            (
+                detect_missing_tick_system(self.schedule_label),
+                shutdown::run_shutdown_callbacks,
+            ),
+        );
+        app.init_resource::<StartupTasks>();
+        app.add_systems(Startup, startup::spawn_startup_tasks);
+    }
+}
+
+/// How long tasks can exist without [`tick_runtime_update`] ever running before
+/// [`detect_missing_tick_system`] reports a likely misconfiguration.
+const MISSING_TICK_SYSTEM_DELAY: Duration = Duration::from_secs(5);
+
+/// Builds a system which logs an error if background tasks have been spawned but the tick counter
+/// has never advanced for several seconds afterwards. This almost always means that the
+/// configured [`schedule_label`](TokioTasksPlugin::schedule_label) is never run, in which case
+/// main thread callbacks and [`sleep_updates`](TaskContext::sleep_updates) never complete. Apps
+/// which never spawn any tasks are never reported.
+fn detect_missing_tick_system(
+    schedule_label: InternedScheduleLabel,
+) -> impl FnMut(Option<Res<TokioTasksRuntime>>, Local<MissingTickCheck>) {
+    move |runtime, mut check| {
+        let Some(runtime) = runtime else {
+            return;
+        };
+        let started_any = runtime.0.shared.registry.started_any();
+        let ticked = runtime.0.shared.ticks.load(Ordering::SeqCst) != runtime.0.shared.initial_tick;
+        if check.should_report(started_any, ticked, Instant::now()) {
+            tracing::error!(
+                "Background tasks were spawned on the TokioTasksRuntime more than {} seconds ago, \
+                but tick_runtime_update has never run, so main thread callbacks and sleep_updates \
+                will never complete. Check that the {:?} schedule configured as the \
+                TokioTasksPlugin's schedule_label is actually run by the app.",
+                MISSING_TICK_SYSTEM_DELAY.as_secs(),
+                schedule_label,
+            );
+        }
     }
 }
 
+/// The state kept by [`detect_missing_tick_system`] between runs.
+#[derive(Default)]
+struct MissingTickCheck {
+    first_task_seen_at: Option<Instant>,
+    /// Set once there is nothing left to report, either because it was reported or because the
+    /// tick system turned out to be running.
+    reported: bool,
+}
+
+impl MissingTickCheck {
+    /// Returns whether the missing tick system should be reported now, given whether any task has
+    /// started and whether the tick counter has ever advanced. This returns `true` at most once.
+    fn should_report(&mut self, started_any: bool, ticked: bool, now: Instant) -> bool {
+        if self.reported || !started_any {
+            return false;
+        }
+        if ticked {
+            // The tick system is running, so there is nothing to report now or in the future.
+            self.reported = true;
+            return false;
+        }
+        let first_task_seen_at = *self.first_task_seen_at.get_or_insert(now);
+        self.reported = now.duration_since(first_task_seen_at) >= MISSING_TICK_SYSTEM_DELAY;
+        self.reported
+    }
+}
+
+/// The Bevy [`SystemSet`]s which [`TokioTasksPlugin`] adds its systems to, for ordering app
+/// systems relative to the runtime.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokioTasksSystems {
+    /// Contains [`tick_runtime_update`], which runs the main thread callbacks queued by tasks.
+    TickRuntimeUpdate,
+}
+
+/// A Bevy [`Resource`] exposing the build-time configuration of the [`TokioTasksPlugin`], so that
+/// other plugins can add their systems relative to the runtime without being told how it was
+/// configured:
+///
+/// ```
+/// # use bevy_app::{App, Plugin};
+/// # use bevy_ecs::schedule::IntoSystemConfigs;
+/// # use bevy_tokio_tasks::{TokioTasksConfig, TokioTasksSystems};
+/// # fn consume_results() {}
+/// struct ResultsPlugin;
+///
+/// impl Plugin for ResultsPlugin {
+///     fn build(&self, app: &mut App) {
+///         let schedule_label = app.world().resource::<TokioTasksConfig>().schedule_label;
+///         app.add_systems(
+///             schedule_label,
+///             consume_results.after(TokioTasksSystems::TickRuntimeUpdate),
+///         );
+///     }
+/// }
+/// ```
+///
+/// This is inserted when the plugin is built, so plugins which read it must be added after the
+/// [`TokioTasksPlugin`].
+#[derive(Resource, Clone, Debug)]
+pub struct TokioTasksConfig {
+    /// The schedule which [`tick_runtime_update`] runs in, as configured by
+    /// [`schedule_label`](TokioTasksPlugin::schedule_label).
+    pub schedule_label: InternedScheduleLabel,
+}
+
 /// The Bevy exclusive system which executes the main thread callbacks that background
 /// tasks requested using [`run_on_main_thread`](TaskContext::run_on_main_thread). You
 /// can control which Bevy schedule stage this system executes in by specifying a custom
 /// [`schedule_label`](TokioTasksPlugin::schedule_label) value.
+///
+/// # Change detection
+///
+/// Changes which callbacks make to the world are ordinary Bevy changes, so they are always seen
+/// by systems using change detection such as [`Changed`](bevy_ecs::query::Changed) or
+/// [`Res::is_changed`](bevy_ecs::change_detection::DetectChanges::is_changed). Whether a system
+/// sees them in the same frame depends on whether it runs after this system:
+///
+/// - Systems in the same schedule which are ordered after
+///   [`TokioTasksSystems::TickRuntimeUpdate`], or systems in a later schedule, see the changes in
+///   the same frame.
+/// - Systems which run before it, or which are unordered relative to it and happen to run
+///   first, see the changes one frame later.
+///
+/// To guarantee same frame visibility for all game logic, either order the systems which react
+/// to task results after [`TokioTasksSystems::TickRuntimeUpdate`], or set
+/// [`schedule_label`](TokioTasksPlugin::schedule_label) to a schedule which runs before the game
+/// logic, such as [`PreUpdate`](bevy_app::PreUpdate) when the game logic is in
+/// [`Update`].
 pub fn tick_runtime_update(world: &mut World) {
+    let Some(current_tick) = advance_and_drain(world) else {
+        return;
+    };
+    let on_tick = world
+        .get_resource::<TokioTasksRuntime>()
+        .and_then(|runtime| runtime.0.on_tick.clone());
+    if let Some(on_tick) = on_tick {
+        on_tick(world, current_tick);
+    }
+}
+
+/// Advances the tick counter and runs the main thread work which is due, returning the new tick,
+/// or [`None`] if the plugin's resources are missing.
+fn advance_and_drain(world: &mut World) -> Option<usize> {
     let current_tick = {
-        let tick_counter = match world.get_resource::<UpdateTicks>() {
-            Some(counter) => counter,
-            None => return,
-        };
+        let tick_counter = world.get_resource::<UpdateTicks>()?;
+        if let Some(time) = world.get_resource::<Time<Real>>() {
+            tick_counter
+                .frame_delta
+                .store(time.delta_secs().to_bits(), Ordering::SeqCst);
+        }
 
         // Increment update ticks and notify watchers of update tick.
         tick_counter.increment_ticks()
     };
 
+    let (has_pending_work, live_task_count) = match world.get_resource::<TokioTasksRuntime>() {
+        Some(runtime) => {
+            runtime.poll_current_thread_tasks();
+            let has_pending_work = runtime.has_pending_main_thread_work();
+            if !has_pending_work {
+                runtime.0.callbacks_last_tick.store(0, Ordering::SeqCst);
+                // A full drain only applies to the tick after it was requested, even if that tick
+                // turns out to have nothing to drain.
+                runtime
+                    .0
+                    .shared
+                    .main_thread_tx
+                    .full_drain_requested
+                    .store(false, Ordering::SeqCst);
+            }
+            (has_pending_work, runtime.live_task_count())
+        }
+        None => return None,
+    };
+
+    if let Some(mut count) = world.get_resource_mut::<LiveTaskCount>() {
+        count.set_if_neq(LiveTaskCount(live_task_count));
+    }
+
+    // Skip moving the runtime out of the world and back on idle ticks, which avoids the cost of
+    // the move as well as needlessly marking the resource as changed.
+    if !has_pending_work {
+        return Some(current_tick);
+    }
+    if world
+        .get_resource::<DrainControl>()
+        .is_some_and(|control| control.paused)
+    {
+        // Ticks keep advancing while paused, but the queued work is held until unpaused. A full
+        // drain request is still used up by this tick, as on idle ticks.
+        let runtime = world.resource::<TokioTasksRuntime>();
+        runtime.0.callbacks_last_tick.store(0, Ordering::SeqCst);
+        runtime
+            .0
+            .shared
+            .main_thread_tx
+            .full_drain_requested
+            .store(false, Ordering::SeqCst);
+        return Some(current_tick);
+    }
+
+    let budget = world
+        .get_resource::<DrainBudget>()
+        .copied()
+        .unwrap_or_default();
     if let Some(mut runtime) = world.remove_resource::<TokioTasksRuntime>() {
-        runtime.execute_main_thread_work(world, current_tick);
+        runtime.execute_main_thread_work(world, current_tick, budget);
         world.insert_resource(runtime);
     }
+    Some(current_tick)
 }
 
-type MainThreadCallback = Box<dyn FnOnce(MainThreadContext) + Send + 'static>;
+/// A one-shot callback which runs on the main thread, as seen by a
+/// [`callback_middleware`](TokioTasksPlugin::callback_middleware).
+pub type MainThreadCallback = Box<dyn FnOnce(MainThreadContext) + Send + 'static>;
+
+/// Wraps every one-shot main thread callback as it is enqueued. See
+/// [`callback_middleware`](TokioTasksPlugin::callback_middleware).
+pub type CallbackMiddleware =
+    Arc<dyn Fn(MainThreadCallback) -> MainThreadCallback + Send + Sync + 'static>;
+
+/// A callback which is invoked on the main thread once per update tick for as long as it keeps
+/// returning `true`.
+type TickCallback = Box<dyn FnMut(MainThreadContext) -> bool + Send + 'static>;
+
+/// The sending half of the queues which carry callbacks to the main thread.
+#[derive(Clone)]
+struct MainThreadSender {
+    update_run_tx: CallbackQueueSender,
+    tick_callback_tx: tokio::sync::mpsc::UnboundedSender<TickCallback>,
+    /// The number of queued callbacks. Together with `tick_callbacks` this lets
+    /// [`tick_runtime_update`] cheaply detect ticks on which there is no work to do.
+    queued_callbacks: Arc<AtomicUsize>,
+    /// The number of queued or registered tick callbacks.
+    tick_callbacks: Arc<AtomicUsize>,
+    scheduled_tx: ScheduledCallbackSender,
+    /// Set by [`request_full_drain_next_tick`](TaskContext::request_full_drain_next_tick) and
+    /// cleared by the next tick.
+    full_drain_requested: Arc<AtomicBool>,
+    shutdown_callbacks: ShutdownCallbacks,
+    callback_middleware: Option<CallbackMiddleware>,
+}
+
+impl MainThreadSender {
+    /// Applies the [`callback_middleware`](TokioTasksPlugin::callback_middleware), if any.
+    fn wrap(&self, callback: MainThreadCallback) -> MainThreadCallback {
+        match &self.callback_middleware {
+            Some(middleware) => middleware(callback),
+            None => callback,
+        }
+    }
+
+    fn send_callback(&self, callback: MainThreadCallback) -> Result<(), MainThreadFailureKind> {
+        let callback = self.wrap(callback);
+        // Count the callback before sending it so the main thread can never observe a callback
+        // which has not been counted yet.
+        self.queued_callbacks.fetch_add(1, Ordering::SeqCst);
+        self.update_run_tx.send(callback).inspect_err(|_| {
+            self.queued_callbacks.fetch_sub(1, Ordering::SeqCst);
+        })
+    }
+
+    fn send_tick_callback(&self, tick_callback: TickCallback) -> Result<(), TickCallback> {
+        self.tick_callbacks.fetch_add(1, Ordering::SeqCst);
+        self.tick_callback_tx.send(tick_callback).map_err(|error| {
+            self.tick_callbacks.fetch_sub(1, Ordering::SeqCst);
+            error.0
+        })
+    }
+}
 
 /// The Bevy [`Resource`] which stores the Tokio [`Runtime`] and allows for spawning new
 /// background tasks.
@@ -110,26 +896,92 @@ pub struct TokioTasksRuntime(Box<TokioTasksRuntimeInner>);
 /// the world in [`tick_runtime_update`].
 struct TokioTasksRuntimeInner {
     runtime: Runtime,
-    ticks: Arc<AtomicUsize>,
+    /// The state which every [`TaskContext`] shares.
+    shared: Arc<SharedContext>,
+    on_tick: Option<TickHook>,
+    #[cfg(feature = "task-logs")]
+    capture_named_task_logs: bool,
+    last_tick_at: Arc<Mutex<Option<Instant>>>,
     update_watch_rx: tokio::sync::watch::Receiver<()>,
-    update_run_tx: tokio::sync::mpsc::UnboundedSender<MainThreadCallback>,
-    update_run_rx: tokio::sync::mpsc::UnboundedReceiver<MainThreadCallback>,
+    callback_queue: Arc<dyn MainThreadQueue>,
+    tick_callback_rx: tokio::sync::mpsc::UnboundedReceiver<TickCallback>,
+    /// Wrapped in a [`Mutex`] only so that the resource is [`Sync`]. It is always accessed
+    /// through [`Mutex::get_mut`], so it is never actually locked.
+    tick_callbacks: Mutex<Vec<TickCallback>>,
+    scheduled_callbacks: ScheduledCallbacks,
+    /// The number of one-shot callbacks which ran during the most recent tick.
+    callbacks_last_tick: Arc<AtomicUsize>,
+    categories: Categories,
+    shutdown_hooks: ShutdownHooks,
+    admission_watermarks: AdmissionWatermarks,
+    #[cfg(not(target_arch = "wasm32"))]
+    ordered_lanes: OrderedLanes,
 }
 
 impl TokioTasksRuntime {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         ticks: Arc<AtomicUsize>,
+        last_tick_at: Arc<Mutex<Option<Instant>>>,
+        frame_delta: Arc<AtomicU32>,
         runtime: Runtime,
         update_watch_rx: tokio::sync::watch::Receiver<()>,
+        sleep_wakeups: Option<Weak<SleepWakeups>>,
+        lossless_tick_rx: Option<tokio::sync::broadcast::Receiver<usize>>,
+        plugin: &TokioTasksPlugin,
     ) -> Self {
-        let (update_run_tx, update_run_rx) = tokio::sync::mpsc::unbounded_channel();
+        let callback_queue = plugin.callback_queue.build(plugin.drain_order);
+        let (scheduled_tx, scheduled_callbacks) = schedule::scheduled_channel();
+        let (tick_callback_tx, tick_callback_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let shared = Arc::new(SharedContext {
+            ticks,
+            initial_tick: plugin.initial_tick,
+            frame_delta,
+            frame_time_budget: plugin.frame_time_budget,
+            main_thread_tx: MainThreadSender {
+                update_run_tx: CallbackQueueSender::new(&callback_queue),
+                tick_callback_tx,
+                queued_callbacks: Arc::new(AtomicUsize::new(0)),
+                tick_callbacks: Arc::new(AtomicUsize::new(0)),
+                scheduled_tx,
+                full_drain_requested: Arc::new(AtomicBool::new(false)),
+                shutdown_callbacks: ShutdownCallbacks::default(),
+                callback_middleware: plugin.callback_middleware.clone(),
+            },
+            request_channels: RequestChannels::default(),
+            registry: Arc::new(TaskRegistry::new(plugin.catch_panics, plugin.executor)),
+            main_thread_failure_message: plugin.main_thread_failure_message.clone(),
+            semaphores: SemaphoreRegistry::new(&plugin.semaphores),
+            services: Arc::new(plugin.services.clone()),
+            notifies: NotifyRegistry::default(),
+            coalesced_keys: CoalescedKeys::default(),
+            resource_folds: ResourceFolds::default(),
+            warn_slow_callback: plugin.warn_slow_callback,
+            sleep_updates_from: plugin.sleep_updates_from,
+            sleep_wakeups,
+            lossless_tick_rx: lossless_tick_rx.map(Arc::new),
+            world_generation: Arc::new(AtomicUsize::new(0)),
+        });
 
         Self(Box::new(TokioTasksRuntimeInner {
             runtime,
-            ticks,
+            shared,
+            on_tick: plugin.on_tick.clone(),
+            #[cfg(feature = "task-logs")]
+            capture_named_task_logs: plugin.capture_named_task_logs,
+            last_tick_at,
             update_watch_rx,
-            update_run_tx,
-            update_run_rx,
+            callback_queue,
+            tick_callback_rx,
+            tick_callbacks: Mutex::new(Vec::new()),
+            scheduled_callbacks,
+            callbacks_last_tick: Arc::new(AtomicUsize::new(0)),
+            categories: Categories::default(),
+            shutdown_hooks: ShutdownHooks::new(),
+            admission_watermarks: plugin.admission_watermarks,
+            #[cfg(not(target_arch = "wasm32"))]
+            ordered_lanes: OrderedLanes::default(),
         }))
     }
 
@@ -143,6 +995,10 @@ impl TokioTasksRuntime {
     /// background task is provided a [`TaskContext`] which allows it to do things like
     /// [sleep for a given number of main thread updates](TaskContext::sleep_updates) or
     /// [invoke callbacks on the main Bevy thread](TaskContext::run_on_main_thread).
+    ///
+    /// Once the runtime is [draining](Self::is_draining) because the app is exiting, the task is
+    /// not run and the returned handle resolves with a cancellation error.
+    #[track_caller]
     pub fn spawn_background_task<Task, Output, Spawnable>(
         &self,
         spawnable_task: Spawnable,
@@ -152,31 +1008,420 @@ impl TokioTasksRuntime {
         Output: Send + 'static,
         Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
     {
-        let inner = &self.0;
-        let context = TaskContext {
-            update_watch_rx: inner.update_watch_rx.clone(),
-            ticks: inner.ticks.clone(),
-            update_run_tx: inner.update_run_tx.clone(),
-        };
-        let future = spawnable_task(context);
-        inner.runtime.spawn(future)
+        self.reserve_task().start(spawnable_task)
+    }
+
+    /// Spawn a task in the same way as [`spawn_background_task`](Self::spawn_background_task),
+    /// but with a human readable name. The name is available to the task through
+    /// [`TaskContext::task_name`] and is included in diagnostics such as
+    /// [`MainThreadFailure`] messages.
+    #[track_caller]
+    pub fn spawn_named_background_task<Task, Output, Spawnable>(
+        &self,
+        name: impl Into<Arc<str>>,
+        spawnable_task: Spawnable,
+    ) -> JoinHandle<Output>
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        self.reserve_task().with_name(name).start(spawnable_task)
+    }
+
+    /// Spawn a follow-up task which waits for the task behind `handle` to finish and then runs
+    /// `continuation` with its result, for building pipelines such as "download, then process,
+    /// then install" without a parent task awaiting each step by hand. The follow-up is a normal
+    /// background task, so it is tracked like any other from the moment this is called, including
+    /// while it waits for the first task.
+    ///
+    /// The result is an [`Err`] if the first task panicked or was aborted, so the continuation can
+    /// decide how to handle failures of the previous step.
+    #[track_caller]
+    pub fn spawn_then<PriorOutput, Task, Output, Continuation>(
+        &self,
+        handle: JoinHandle<PriorOutput>,
+        continuation: Continuation,
+    ) -> JoinHandle<Output>
+    where
+        PriorOutput: Send + 'static,
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Continuation: FnOnce(Result<PriorOutput, tokio::task::JoinError>, TaskContext) -> Task
+            + Send
+            + 'static,
+    {
+        self.spawn_background_task(move |ctx| async move {
+            let result = handle.await;
+            continuation(result, ctx).await
+        })
+    }
+
+    /// Spawn a task which builds a resource asynchronously, such as a connection pool, and then
+    /// inserts its output into the main Bevy [`World`] as a resource on the next tick after it
+    /// completes. Systems can use `Option<Res<R>>` to check whether the resource is ready yet.
+    /// The resource replaces any existing resource of the same type.
+    ///
+    /// Nothing is inserted if the task panics or is aborted.
+    #[track_caller]
+    pub fn spawn_into_resource<R, Task, Spawnable>(
+        &self,
+        spawnable_task: Spawnable,
+    ) -> JoinHandle<()>
+    where
+        R: Resource,
+        Task: Future<Output = R> + Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        self.spawn_background_task(move |mut ctx| async move {
+            let resource = spawnable_task(ctx.clone()).await;
+            ctx.run_on_main_thread(move |ctx| ctx.world.insert_resource(resource))
+                .await;
+        })
+    }
+
+    /// Creates the [`TaskContext`] which is given to a newly spawned task.
+    fn new_task_context(&self, task_id: TaskId) -> TaskContext {
+        TaskContext {
+            task_id,
+            task_name: None,
+            cancellation_token: None,
+            category: None,
+            update_watch_rx: self.0.update_watch_rx.clone(),
+            shared: self.0.shared.clone(),
+        }
+    }
+
+    /// Advances Tokio's paused clock by the given duration, firing any timers which elapse and
+    /// letting the tasks waiting on them run. This requires a runtime with a paused clock, such as
+    /// the one configured by [`TokioTasksPlugin::deterministic`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime's clock is not paused.
+    #[cfg(feature = "test-util")]
+    pub fn advance_time(&self, duration: std::time::Duration) {
+        self.0.runtime.block_on(tokio::time::advance(duration));
+    }
+
+    /// Queues a callback which runs once with mutable access to the [`World`] during the next
+    /// execution of [`tick_runtime_update`]. This is a convenience for deferring a single world
+    /// mutation from places where [`Commands`](bevy_ecs::system::Commands) are not available,
+    /// without spawning a task. The returned [`MainThreadJoin`] can be awaited from a task to wait
+    /// for the callback to complete, or simply dropped.
+    #[track_caller]
+    pub fn run_once_next_tick<Runnable>(&self, runnable: Runnable) -> MainThreadJoin<()>
+    where
+        Runnable: FnOnce(&mut World) + Send + 'static,
+    {
+        enqueue_callback(
+            &self.0.shared.main_thread_tx,
+            self.main_thread_origin(Location::caller()),
+            IfAbandoned::Run,
+            move |ctx| runnable(ctx.world),
+        )
     }
 
-    /// Execute all of the requested runnables on the main thread.
-    pub(crate) fn execute_main_thread_work(&mut self, world: &mut World, current_tick: usize) {
+    /// Describes a callback requested from the main thread rather than by a task.
+    fn main_thread_origin(&self, location: &'static Location<'static>) -> CallbackOrigin {
+        CallbackOrigin {
+            formatter: self.0.shared.main_thread_failure_message.clone(),
+            task_id: None,
+            task_name: None,
+            category: None,
+            registry: None,
+            location,
+            warn_slow_callback: self.0.shared.warn_slow_callback,
+        }
+    }
+
+    /// Gives the runtime a chance to make progress on its tasks.
+    fn poll_current_thread_tasks(&self) {
         // Running this single future which yields once allows the runtime to process tasks
         // if the runtime is a current_thread runtime. If its a multi-thread runtime then
         // this isn't necessary but is harmless.
         self.0.runtime.block_on(async {
             tokio::task::yield_now().await;
         });
-        while let Ok(runnable) = self.0.update_run_rx.try_recv() {
+    }
+
+    /// Returns whether there are any queued callbacks or registered tick callbacks.
+    fn has_pending_main_thread_work(&self) -> bool {
+        let main_thread_tx = &self.0.shared.main_thread_tx;
+        main_thread_tx.queued_callbacks.load(Ordering::SeqCst) > 0
+            || main_thread_tx.tick_callbacks.load(Ordering::SeqCst) > 0
+            || self
+                .0
+                .scheduled_callbacks
+                .has_due(self.0.shared.ticks.load(Ordering::SeqCst))
+    }
+
+    /// Execute the requested runnables on the main thread, up to the limits of the budget.
+    pub(crate) fn execute_main_thread_work(
+        &mut self,
+        world: &mut World,
+        current_tick: usize,
+        budget: DrainBudget,
+    ) {
+        let queued_callbacks = &self.0.shared.main_thread_tx.queued_callbacks;
+        let catch_panics = self.0.shared.registry.catch_panics();
+        let budget = if self
+            .0
+            .shared
+            .main_thread_tx
+            .full_drain_requested
+            .swap(false, Ordering::SeqCst)
+        {
+            DrainBudget::UNLIMITED
+        } else {
+            budget
+        };
+        let started_at = Instant::now();
+        let mut drained = 0;
+        while budget.allows(drained, started_at) {
+            let Some(runnable) = self.0.callback_queue.pop() else {
+                break;
+            };
+            drained += 1;
+            queued_callbacks.fetch_sub(1, Ordering::SeqCst);
             let context = MainThreadContext {
                 world,
                 current_tick,
             };
-            runnable(context);
+            panic::run_callback(catch_panics, || runnable(context));
+        }
+        // Callbacks scheduled for this tick are not limited by the budget, so that they run on
+        // exactly the tick they were scheduled for.
+        for runnable in self.0.scheduled_callbacks.take_due(current_tick) {
+            drained += 1;
+            panic::run_callback(catch_panics, || {
+                runnable(MainThreadContext {
+                    world,
+                    current_tick,
+                })
+            });
+        }
+        self.0.callbacks_last_tick.store(drained, Ordering::SeqCst);
+        // Per-tick callbacks run after the one-shot callbacks so that they observe any world
+        // changes made by this tick's callbacks.
+        let tick_callbacks = self
+            .0
+            .tick_callbacks
+            .get_mut()
+            .expect("Tick callbacks mutex was poisoned");
+        while let Ok(tick_callback) = self.0.tick_callback_rx.try_recv() {
+            tick_callbacks.push(tick_callback);
+        }
+        let registered_tick_callbacks = &self.0.shared.main_thread_tx.tick_callbacks;
+        tick_callbacks.retain_mut(|tick_callback| {
+            // A tick callback which panics is unregistered, since it would most likely panic again.
+            let keep = panic::run_callback(catch_panics, || {
+                tick_callback(MainThreadContext {
+                    world,
+                    current_tick,
+                })
+            })
+            .unwrap_or(false);
+            if !keep {
+                registered_tick_callbacks.fetch_sub(1, Ordering::SeqCst);
+            }
+            keep
+        });
+    }
+}
+
+/// Formats the panic message for a [`MainThreadFailure`]. See
+/// [`main_thread_failure_message`](TokioTasksPlugin::main_thread_failure_message).
+pub type MainThreadFailureFormatter =
+    Arc<dyn Fn(&MainThreadFailure) -> String + Send + Sync + 'static>;
+
+/// Describes an unrecoverable failure of a [`run_on_main_thread`](TaskContext::run_on_main_thread)
+/// call. These are passed to the
+/// [`main_thread_failure_message`](TokioTasksPlugin::main_thread_failure_message) formatter to
+/// build the message of the resulting panic.
+#[derive(Debug)]
+pub struct MainThreadFailure<'a> {
+    /// Which step of the main thread round trip failed.
+    pub kind: MainThreadFailureKind,
+    /// The id of the task which requested the callback, or [`None`] if it was requested from the
+    /// main thread using [`run_once_next_tick`](TokioTasksRuntime::run_once_next_tick).
+    pub task_id: Option<TaskId>,
+    /// The name of the task which requested the callback, if it has one.
+    pub task_name: Option<&'a str>,
+    /// The location from which [`run_on_main_thread`](TaskContext::run_on_main_thread) was called.
+    pub location: &'static Location<'static>,
+}
+
+impl std::fmt::Display for MainThreadFailure<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self.kind {
+            MainThreadFailureKind::Enqueue => "Failed to send operation to be run on main thread",
+            MainThreadFailureKind::QueueFull => {
+                "Failed to send operation to be run on main thread because the queue is full"
+            }
+            MainThreadFailureKind::ReceiveOutput => {
+                "Failed to receive output from operation on main thread"
+            }
+        };
+        match self.task_id {
+            Some(task_id) => write!(f, "{description} (requested by {task_id}")?,
+            None => write!(f, "{description} (requested from the main thread")?,
+        }
+        if let Some(task_name) = self.task_name {
+            write!(f, " \"{task_name}\"")?;
+        }
+        write!(f, " at {})", self.location)
+    }
+}
+
+/// The step of a [`run_on_main_thread`](TaskContext::run_on_main_thread) call which failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MainThreadFailureKind {
+    /// The callback could not be sent to the main thread, because the [`TokioTasksRuntime`] which
+    /// receives callbacks no longer exists.
+    Enqueue,
+    /// The callback could not be sent to the main thread, because the plugin's
+    /// [`callback_queue`](TokioTasksPlugin::callback_queue) is [bounded](CallbackQueueKind::Bounded)
+    /// and already full.
+    QueueFull,
+    /// The callback was dropped by the main thread without being run.
+    ReceiveOutput,
+}
+
+/// What the main thread does with a queued callback when it finds that nothing is waiting for the
+/// callback's output any more, because the [`MainThreadJoin`] was dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum IfAbandoned {
+    /// Run the callback anyway and discard its output. This suits fire-and-forget callbacks.
+    Run,
+    /// Skip the callback, so that a task which was aborted while waiting does not go on to
+    /// mutate the world.
+    Skip,
+}
+
+/// Queues a callback on the main thread, returning a [`MainThreadJoin`] which resolves with its
+/// output.
+fn enqueue_callback<Runnable, Output>(
+    main_thread_tx: &MainThreadSender,
+    origin: CallbackOrigin,
+    if_abandoned: IfAbandoned,
+    runnable: Runnable,
+) -> MainThreadJoin<Output>
+where
+    Runnable: FnOnce(MainThreadContext) -> Output + Send + 'static,
+    Output: Send + 'static,
+{
+    let (callback, join) = join_callback(origin, if_abandoned, runnable);
+    if let Err(kind) = main_thread_tx.send_callback(callback) {
+        join.origin.fail(kind);
+    }
+    join
+}
+
+/// Wraps a runnable into a callback which sends its output to the returned [`MainThreadJoin`].
+fn join_callback<Runnable, Output>(
+    origin: CallbackOrigin,
+    if_abandoned: IfAbandoned,
+    runnable: Runnable,
+) -> (MainThreadCallback, MainThreadJoin<Output>)
+where
+    Runnable: FnOnce(MainThreadContext) -> Output + Send + 'static,
+    Output: Send + 'static,
+{
+    let (output_tx, output_rx) = tokio::sync::oneshot::channel();
+    let callback_origin = origin.clone();
+    let category = origin.category.clone();
+    let queued = category.as_ref().map(Category::queued_callback);
+    let callback: MainThreadCallback = Box::new(move |ctx| {
+        drop(queued);
+        if if_abandoned == IfAbandoned::Skip && output_tx.is_closed() {
+            return;
+        }
+        if let Some(category) = &category {
+            category.record_run(ctx.current_tick);
+        }
+        // Nobody is waiting for the output if the task was aborted or the join handle was
+        // dropped, in which case the output is simply discarded.
+        let _ = output_tx.send(callback_origin.run(|| runnable(ctx)));
+    });
+    (callback, MainThreadJoin { output_rx, origin })
+}
+
+/// Describes where a main thread callback was requested from. This has everything needed to format
+/// a [`MainThreadFailure`] after the [`TaskContext`] which requested the callback is no longer
+/// accessible, such as from inside the callback itself.
+#[derive(Clone)]
+struct CallbackOrigin {
+    formatter: MainThreadFailureFormatter,
+    task_id: Option<TaskId>,
+    task_name: Option<Arc<str>>,
+    category: Option<Arc<Category>>,
+    /// The registry which the callback's running time is attributed to the requesting task in.
+    /// See [`task_main_thread_cost`](TokioTasksRuntime::task_main_thread_cost).
+    registry: Option<Arc<TaskRegistry>>,
+    location: &'static Location<'static>,
+    /// See [`warn_slow_callback`](TokioTasksPlugin::warn_slow_callback).
+    warn_slow_callback: Option<Duration>,
+}
+
+impl CallbackOrigin {
+    /// Runs the body of a callback requested from this origin, inside its span when the `trace`
+    /// feature is enabled, attributes its running time to the requesting task, and warns if it
+    /// takes longer than the slow callback threshold.
+    fn run<Output>(&self, callback: impl FnOnce() -> Output) -> Output {
+        #[cfg(feature = "trace")]
+        let _span = self.span().entered();
+        let started_at = Instant::now();
+        let output = callback();
+        let elapsed = started_at.elapsed();
+        if let (Some(registry), Some(task_id)) = (&self.registry, self.task_id) {
+            registry.record_main_thread_cost(task_id, elapsed);
+        }
+        let Some(threshold) = self.warn_slow_callback else {
+            return output;
+        };
+        if elapsed > threshold {
+            let requester = match self.task_id {
+                Some(task_id) => task_id.to_string(),
+                None => "the main thread".to_string(),
+            };
+            let name = self
+                .task_name
+                .as_deref()
+                .map(|name| format!(" \"{name}\""))
+                .unwrap_or_default();
+            tracing::warn!(
+                "Main thread callback requested by {requester}{name} at {} took {elapsed:?}, \
+                more than the {threshold:?} threshold",
+                self.location,
+            );
         }
+        output
+    }
+
+    /// Creates the span which a callback requested from this origin runs in. The span's parent is
+    /// whichever span is current when the callback runs, which is the span of the
+    /// [`tick_runtime_update`] system when Bevy's tracing is enabled, so traces show each callback
+    /// nested under the frame it ran in.
+    #[cfg(feature = "trace")]
+    fn span(&self) -> tracing::Span {
+        tracing::info_span!(
+            parent: &tracing::Span::current(),
+            "main_thread_callback",
+            task = ?self.task_id,
+            task_name = ?self.task_name,
+            location = %self.location,
+        )
+    }
+
+    fn fail(&self, kind: MainThreadFailureKind) -> ! {
+        let message = (self.formatter)(&MainThreadFailure {
+            kind,
+            task_id: self.task_id,
+            task_name: self.task_name.as_deref(),
+            location: self.location,
+        });
+        panic!("{message}");
     }
 }
 
@@ -193,53 +1438,983 @@ pub struct MainThreadContext<'a> {
 /// [`TokioTasksRuntime`].
 #[derive(Clone)]
 pub struct TaskContext {
+    task_id: TaskId,
+    task_name: Option<Arc<str>>,
+    cancellation_token: Option<CancellationToken>,
+    category: Option<Arc<Category>>,
+    /// Kept per context rather than shared, since waiting on it marks ticks as seen.
     update_watch_rx: tokio::sync::watch::Receiver<()>,
-    update_run_tx: tokio::sync::mpsc::UnboundedSender<MainThreadCallback>,
+    shared: Arc<SharedContext>,
+}
+
+/// The parts of a [`TaskContext`] which are the same for every task. They are shared behind a
+/// single [`Arc`], so that cloning a context for a new task is cheap.
+struct SharedContext {
     ticks: Arc<AtomicUsize>,
+    /// See [`initial_tick`](TokioTasksPlugin::initial_tick).
+    initial_tick: usize,
+    frame_delta: Arc<AtomicU32>,
+    frame_time_budget: Option<Duration>,
+    main_thread_tx: MainThreadSender,
+    request_channels: RequestChannels,
+    registry: Arc<TaskRegistry>,
+    main_thread_failure_message: MainThreadFailureFormatter,
+    semaphores: SemaphoreRegistry,
+    services: Arc<TaskServices>,
+    notifies: NotifyRegistry,
+    coalesced_keys: CoalescedKeys,
+    resource_folds: ResourceFolds,
+    warn_slow_callback: Option<Duration>,
+    sleep_updates_from: SleepUpdatesFrom,
+    sleep_wakeups: Option<Weak<SleepWakeups>>,
+    /// Never received from. Lossless tick streams are created by resubscribing to it, and since
+    /// receivers do not keep the channel open, the streams end when the main thread shuts down.
+    lossless_tick_rx: Option<Arc<tokio::sync::broadcast::Receiver<usize>>>,
+    world_generation: Arc<AtomicUsize>,
 }
 
 impl TaskContext {
+    /// Returns the id of the task which this context was created for. Clones of a context share
+    /// the id of the task it was originally created for.
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+
+    /// Returns the name of the task which this context was created for, if it was spawned using
+    /// [`spawn_named_background_task`](TokioTasksRuntime::spawn_named_background_task) or
+    /// [`TaskSlot::with_name`].
+    pub fn task_name(&self) -> Option<&str> {
+        self.task_name.as_deref()
+    }
+
     /// Returns the current value of the ticket count from the main thread - how many updates
-    /// have occurred since the start of the program. Because the tick count is updated from the
+    /// have occurred since the start of the program, counting up from
+    /// [`initial_tick`](TokioTasksPlugin::initial_tick). Because the tick count is updated from the
     /// main thread, the tick count may change any time after this function call returns.
     pub fn current_tick(&self) -> usize {
-        self.ticks.load(Ordering::SeqCst)
+        self.shared.ticks.load(Ordering::SeqCst)
+    }
+
+    /// Returns the current tick like [`current_tick`](Self::current_tick), or [`None`] once the
+    /// app is shutting down, meaning that the runtime is
+    /// [draining](TokioTasksRuntime::is_draining) or the main thread has already stopped. This
+    /// gives tasks which poll the tick in a loop a clean signal to stop, without checking for
+    /// shutdown separately.
+    pub fn try_current_tick(&self) -> Option<usize> {
+        // The update channel closes once the main thread has shut down.
+        if self.shared.registry.is_draining() || self.update_watch_rx.has_changed().is_err() {
+            return None;
+        }
+        Some(self.current_tick())
+    }
+
+    /// Returns the real, wall-clock duration in seconds of the last completed frame, as reported
+    /// by Bevy's [`Time<Real>`] clock. Unlike the gameplay [`Time`], this is not affected by pausing
+    /// or scaling virtual time, which makes it suitable for animations driven from async code.
+    /// The value is snapshotted by [`tick_runtime_update`] on each tick, so reading it is cheap and
+    /// does not need a round trip to the main thread. It is zero until the first tick and if the
+    /// app does not have Bevy's `TimePlugin`.
+    pub fn frame_delta_seconds(&self) -> f32 {
+        f32::from_bits(self.shared.frame_delta.load(Ordering::SeqCst))
+    }
+
+    /// Returns a clone of the watch channel receiver which is notified on every update tick. This
+    /// is a lower-level escape hatch for building custom tick-based primitives, such as waiting
+    /// on ticks and other events together in a `select!`, without reimplementing
+    /// [`sleep_updates`](Self::sleep_updates).
+    ///
+    /// The channel carries no value and notifications coalesce, so a receiver which falls behind
+    /// is only woken once for several ticks. Combine it with [`current_tick`](Self::current_tick)
+    /// to find out which tick has been reached, which is the supported way to build custom
+    /// tick-waiting logic. The channel closes once the main thread has shut down.
+    pub fn tick_watch(&self) -> tokio::sync::watch::Receiver<()> {
+        self.update_watch_rx.clone()
+    }
+
+    /// Makes the next update tick ignore the [`DrainBudget`] and run every queued callback, for
+    /// flushing a large batch of results with the lowest possible latency without permanently
+    /// raising the budget. The request is cleared by the next tick whether or not it had any
+    /// callbacks to run, or was [paused](DrainControl), and later ticks use the budget as normal
+    /// again.
+    ///
+    /// Call this after queueing the batch's callbacks, since a tick which happens in between
+    /// would use up the request.
+    pub fn request_full_drain_next_tick(&self) {
+        self.shared
+            .main_thread_tx
+            .full_drain_requested
+            .store(true, Ordering::SeqCst);
     }
 
     /// Sleeps the background task until a given number of main thread updates have occurred. If
     /// you instead want to sleep for a given length of wall-clock time, call the normal Tokio sleep
     /// function.
+    ///
+    /// The main thread notifies sleeping tasks once per update tick, and notifications which
+    /// arrive while the task is not waiting collapse into a single wakeup. A sleeping task is
+    /// therefore woken at most once per tick no matter how many tasks are sleeping, and each
+    /// wakeup only compares the tick counter against the target tick.
+    ///
+    /// Exactly which tick the task wakes on is controlled by
+    /// [`sleep_updates_from`](TokioTasksPlugin::sleep_updates_from). With the default of
+    /// [`SleepUpdatesFrom::CurrentTick`], sleeping for zero updates returns immediately.
     pub async fn sleep_updates(&mut self, updates_to_sleep: usize) {
-        let target_tick = self
-            .ticks
-            .load(Ordering::SeqCst)
-            .wrapping_add(updates_to_sleep);
-        while self.ticks.load(Ordering::SeqCst) < target_tick {
-            if self.update_watch_rx.changed().await.is_err() {
-                return;
+        let updates_to_sleep = match self.shared.sleep_updates_from {
+            SleepUpdatesFrom::CurrentTick => updates_to_sleep,
+            SleepUpdatesFrom::NextTick => updates_to_sleep.saturating_add(1),
+        };
+        let current_tick = self.shared.ticks.load(Ordering::SeqCst);
+        let target_tick = current_tick.wrapping_add(updates_to_sleep);
+        if let Some(sleep_wakeups) = &self.shared.sleep_wakeups {
+            if target_tick != current_tick {
+                sleep::sleep_until(sleep_wakeups, target_tick).await;
             }
+            return;
         }
+        let ticks = &self.shared.ticks;
+        // Compared relative to the starting tick, so that a counter which wraps around does not
+        // make the target look like it has already passed.
+        // An error means the main thread has shut down and no more ticks will occur.
+        let _ = self
+            .update_watch_rx
+            .wait_for(|_| {
+                ticks.load(Ordering::SeqCst).wrapping_sub(current_tick) >= updates_to_sleep
+            })
+            .await;
+    }
+
+    /// Waits until the app has started running its update loop, meaning that
+    /// [`tick_runtime_update`] has ticked at least once. Tasks spawned during
+    /// [`Startup`](bevy_app::Startup) can run before the first tick, while
+    /// [`current_tick`](Self::current_tick) is still at its
+    /// [initial value](TokioTasksPlugin::initial_tick) and the world may still be initializing,
+    /// so this gives them a clear signal to defer their work until the main loop is spinning.
+    ///
+    /// Unlike [`sleep_updates(1)`](Self::sleep_updates), this does not wait for another tick if
+    /// the update loop is already running, so it returns immediately for tasks which are spawned
+    /// later on.
+    pub async fn wait_for_first_tick(&mut self) {
+        let ticks = &self.shared.ticks;
+        let initial_tick = self.shared.initial_tick;
+        // An error means the main thread has shut down and no more ticks will occur.
+        let _ = self
+            .update_watch_rx
+            .wait_for(|_| ticks.load(Ordering::SeqCst) != initial_tick)
+            .await;
     }
 
     /// Invokes a synchronous callback on the main Bevy thread. The callback will have mutable access to the
     /// main Bevy [`World`], allowing it to update any resources or entities that it wants. The callback can
     /// report results back to the background thread by returning an output value, which will then be returned from
     /// this async function once the callback runs.
-    pub async fn run_on_main_thread<Runnable, Output>(&mut self, runnable: Runnable) -> Output
+    ///
+    /// If the returned future is dropped before the main thread gets to the callback, for example
+    /// because the task was aborted, the callback is skipped and never runs.
+    ///
+    /// If the callback cannot be run, for example because the main thread dropped it, this panics
+    /// with a message formatted by
+    /// [`main_thread_failure_message`](TokioTasksPlugin::main_thread_failure_message). The panic
+    /// reports the location of the caller of this function.
+    #[track_caller]
+    pub fn run_on_main_thread<Runnable, Output>(
+        &mut self,
+        runnable: Runnable,
+    ) -> impl Future<Output = Output> + Send + '_
     where
         Runnable: FnOnce(MainThreadContext) -> Output + Send + 'static,
         Output: Send + 'static,
     {
+        let location = Location::caller();
+        async move { self.enqueue_at(runnable, location, IfAbandoned::Skip).await }
+    }
+
+    /// Queues a synchronous callback to run on the main Bevy thread in the same way as
+    /// [`run_on_main_thread`](Self::run_on_main_thread), but returns immediately instead of
+    /// waiting for the callback to run. The returned [`MainThreadJoin`] can be awaited later to
+    /// receive the callback's output, which allows a task to queue several callbacks and then
+    /// wait for all of them together.
+    ///
+    /// Unlike with [`run_on_main_thread`](Self::run_on_main_thread), the callback still runs if
+    /// the [`MainThreadJoin`] is dropped, so this can also be used for fire-and-forget callbacks.
+    #[track_caller]
+    pub fn enqueue_main_thread<Runnable, Output>(
+        &self,
+        runnable: Runnable,
+    ) -> MainThreadJoin<Output>
+    where
+        Runnable: FnOnce(MainThreadContext) -> Output + Send + 'static,
+        Output: Send + 'static,
+    {
+        self.enqueue_at(runnable, Location::caller(), IfAbandoned::Run)
+    }
+
+    fn enqueue_at<Runnable, Output>(
+        &self,
+        runnable: Runnable,
+        location: &'static Location<'static>,
+        if_abandoned: IfAbandoned,
+    ) -> MainThreadJoin<Output>
+    where
+        Runnable: FnOnce(MainThreadContext) -> Output + Send + 'static,
+        Output: Send + 'static,
+    {
+        enqueue_callback(
+            &self.shared.main_thread_tx,
+            self.callback_origin(location),
+            if_abandoned,
+            runnable,
+        )
+    }
+
+    /// Moves a value to the main thread, runs a callback which consumes it there, and moves the
+    /// callback's output back to this task. This behaves like
+    /// [`run_on_main_thread`](Self::run_on_main_thread), but expresses the common pattern of
+    /// handing a large value such as a buffer to the main thread without cloning it, and
+    /// receiving it or a transformed version of it back.
+    #[track_caller]
+    pub fn hand_off<In, Out, Runnable>(
+        &mut self,
+        value: In,
+        runnable: Runnable,
+    ) -> impl Future<Output = Out> + Send + '_
+    where
+        In: Send + 'static,
+        Out: Send + 'static,
+        Runnable: FnOnce(In, MainThreadContext) -> Out + Send + 'static,
+    {
+        self.run_on_main_thread(move |ctx| runnable(value, ctx))
+    }
+
+    fn callback_origin(&self, location: &'static Location<'static>) -> CallbackOrigin {
+        CallbackOrigin {
+            formatter: self.shared.main_thread_failure_message.clone(),
+            task_id: Some(self.task_id),
+            task_name: self.task_name.clone(),
+            category: self.category.clone(),
+            registry: Some(self.shared.registry.clone()),
+            location,
+            warn_slow_callback: self.shared.warn_slow_callback,
+        }
+    }
+
+    /// Registers a projection which derives a value from the main Bevy [`World`] on every update
+    /// tick. The projection closure runs on the main thread during [`tick_runtime_update`], and the
+    /// returned [`WorldProjection`] can be used to read the most recently computed value without
+    /// waiting for a main thread callback of its own. The projection is unregistered once the
+    /// [`WorldProjection`] is dropped.
+    #[track_caller]
+    pub fn world_projection<T, Projection>(&mut self, projection: Projection) -> WorldProjection<T>
+    where
+        T: Send + Sync + 'static,
+        Projection: Fn(&World) -> T + Send + 'static,
+    {
+        let origin = self.callback_origin(Location::caller());
+        let (projection_tx, projection_rx) = tokio::sync::watch::channel(None);
+        let callback_origin = origin.clone();
+        let tick_callback: TickCallback = Box::new(move |ctx| {
+            callback_origin.run(|| {
+                if projection_tx.is_closed() {
+                    return false;
+                }
+                projection_tx.send_replace(Some(projection(ctx.world)));
+                true
+            })
+        });
+        if self
+            .shared
+            .main_thread_tx
+            .send_tick_callback(tick_callback)
+            .is_err()
+        {
+            origin.fail(MainThreadFailureKind::Enqueue);
+        }
+        WorldProjection { projection_rx }
+    }
+
+    /// Spreads main thread work across several frames. The `step` closure runs on the main
+    /// thread once per update tick, receiving the `chunk_budget` so it can do a bounded amount of
+    /// work, such as processing up to that many items, before returning. It should return `true`
+    /// once all of the work is done, at which point the returned future resolves. This is a
+    /// structured version of interleaving [`sleep_updates`](Self::sleep_updates) with
+    /// [`run_on_main_thread`](Self::run_on_main_thread) calls by hand.
+    ///
+    /// The first step runs on the tick after this is called. Dropping the returned future stops
+    /// any further steps from running.
+    #[track_caller]
+    pub fn drive_incremental<Step>(
+        &mut self,
+        chunk_budget: usize,
+        mut step: Step,
+    ) -> MainThreadJoin<()>
+    where
+        Step: FnMut(MainThreadContext, usize) -> bool + Send + 'static,
+    {
+        self.run_each_tick_until(
+            move |ctx| step(ctx, chunk_budget).then_some(()),
+            Location::caller(),
+        )
+    }
+
+    /// Applies a collection of items to the world across several frames, for bulk data which would
+    /// cause a hitch if it was all applied at once. The `apply` closure runs on the main thread
+    /// once per update tick with the next `chunk_size` items, in order, and the returned future
+    /// resolves once every item has been applied. The last chunk may be smaller, and a chunk size
+    /// of zero is treated as one. This is a ready-made version of
+    /// [`drive_incremental`](Self::drive_incremental) for the common case of working through a
+    /// [`Vec`].
+    ///
+    /// The first chunk is applied on the tick after this is called. If `items` is empty, `apply`
+    /// is never called and the future resolves on that tick. Dropping the returned future stops
+    /// any further chunks from being applied, and the remaining items are dropped with it.
+    #[track_caller]
+    pub fn apply_chunked<T, Apply>(
+        &mut self,
+        items: Vec<T>,
+        chunk_size: usize,
+        mut apply: Apply,
+    ) -> MainThreadJoin<()>
+    where
+        T: Send + 'static,
+        Apply: FnMut(Vec<T>, MainThreadContext) + Send + 'static,
+    {
+        let chunk_size = chunk_size.max(1);
+        let mut remaining = items.into_iter();
+        self.run_each_tick_until(
+            move |ctx| {
+                let chunk: Vec<T> = remaining.by_ref().take(chunk_size).collect();
+                if !chunk.is_empty() {
+                    apply(chunk, ctx);
+                }
+                (remaining.len() == 0).then_some(())
+            },
+            Location::caller(),
+        )
+    }
+
+    /// Runs a stateful callback on the main thread once per update tick, starting with the tick
+    /// after this is called, until it returns [`ControlFlow::Break`], at which point the returned
+    /// future resolves with the break value. `state` is moved into the callback once and is
+    /// passed to every invocation, so multi-tick main thread routines can keep their own state
+    /// without storing it in a resource between ticks.
+    ///
+    /// Dropping the returned future stops any further invocations, and the state is dropped with
+    /// the callback.
+    #[track_caller]
+    pub fn run_on_main_thread_repeating<State, Callback, Output>(
+        &mut self,
+        mut state: State,
+        mut callback: Callback,
+    ) -> MainThreadJoin<Output>
+    where
+        State: Send + 'static,
+        Callback: FnMut(MainThreadContext, &mut State) -> ControlFlow<Output> + Send + 'static,
+        Output: Send + 'static,
+    {
+        self.run_each_tick_until(
+            move |ctx| match callback(ctx, &mut state) {
+                ControlFlow::Break(output) => Some(output),
+                ControlFlow::Continue(()) => None,
+            },
+            Location::caller(),
+        )
+    }
+
+    /// Registers a tick callback which runs on every update tick until it returns an output,
+    /// which the returned [`MainThreadJoin`] then resolves with. The callback is unregistered
+    /// early if the [`MainThreadJoin`] is dropped.
+    fn run_each_tick_until<Callback, Output>(
+        &self,
+        mut callback: Callback,
+        location: &'static Location<'static>,
+    ) -> MainThreadJoin<Output>
+    where
+        Callback: FnMut(MainThreadContext) -> Option<Output> + Send + 'static,
+        Output: Send + 'static,
+    {
+        let origin = self.callback_origin(location);
         let (output_tx, output_rx) = tokio::sync::oneshot::channel();
-        if self.update_run_tx.send(Box::new(move |ctx| {
-            if output_tx.send(runnable(ctx)).is_err() {
-                panic!("Failed to sent output from operation run on main thread back to waiting task");
-            }
-        })).is_err() {
-            panic!("Failed to send operation to be run on main thread");
+        let mut output_tx = Some(output_tx);
+        let callback_origin = origin.clone();
+        let tick_callback: TickCallback = Box::new(move |ctx| {
+            callback_origin.run(|| {
+                if output_tx
+                    .as_ref()
+                    .is_none_or(|output_tx| output_tx.is_closed())
+                {
+                    return false;
+                }
+                match callback(ctx) {
+                    Some(output) => {
+                        if let Some(output_tx) = output_tx.take() {
+                            let _ = output_tx.send(output);
+                        }
+                        false
+                    }
+                    None => true,
+                }
+            })
+        });
+        if self
+            .shared
+            .main_thread_tx
+            .send_tick_callback(tick_callback)
+            .is_err()
+        {
+            origin.fail(MainThreadFailureKind::Enqueue);
         }
-        output_rx
+        MainThreadJoin { output_rx, origin }
+    }
+}
+
+/// Resolves with the output of main thread work, such as a callback queued using
+/// [`enqueue_main_thread`](TaskContext::enqueue_main_thread), once the work has completed.
+///
+/// Dropping this without awaiting it does not cancel a callback queued using
+/// [`enqueue_main_thread`](TaskContext::enqueue_main_thread), but its output is discarded. Work
+/// which runs across several ticks, such as
+/// [`drive_incremental`](TaskContext::drive_incremental), stops when this is dropped.
+#[must_use = "outputs are lost, and multi-tick work stops, if this is dropped without being awaited"]
+pub struct MainThreadJoin<Output> {
+    output_rx: tokio::sync::oneshot::Receiver<Output>,
+    origin: CallbackOrigin,
+}
+
+impl<Output> Future for MainThreadJoin<Output> {
+    type Output = Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Output> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.output_rx).poll(cx) {
+            Poll::Ready(Ok(output)) => Poll::Ready(output),
+            Poll::Ready(Err(_)) => this.origin.fail(MainThreadFailureKind::ReceiveOutput),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A handle to a projection of the main Bevy [`World`] registered using
+/// [`world_projection`](TaskContext::world_projection). The projection is recomputed on every
+/// update tick for as long as this handle is alive.
+pub struct WorldProjection<T> {
+    projection_rx: tokio::sync::watch::Receiver<Option<T>>,
+}
+
+impl<T: Clone> WorldProjection<T> {
+    /// Returns the most recent value computed by the projection. If the projection has not run
+    /// yet, this waits until the first main thread update tick computes it.
+    pub async fn latest(&mut self) -> T {
+        let latest = self
+            .projection_rx
+            .wait_for(Option::is_some)
             .await
-            .expect("Failed to receive output from operation on main thread")
+            .expect("Failed to receive world projection from main thread");
+        latest
+            .clone()
+            .expect("World projection was empty after waiting for a value")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::ops::ControlFlow;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use bevy_app::{App, AppExit, FixedUpdate};
+    use bevy_ecs::schedule::ScheduleLabel;
+    use bevy_ecs::system::Resource;
+    use bevy_time::{Fixed, Time, TimePlugin, TimeUpdateStrategy};
+    use bevy_utils::Instant;
+
+    use crate::{
+        testing, MainThreadJoin, MissingTickCheck, SleepUpdatesFrom, TaskContext, TokioTasksPlugin,
+        TokioTasksRuntime, WorldProjection, MISSING_TICK_SYSTEM_DELAY,
+    };
+
+    #[test]
+    fn fixed_update_ticks_once_per_fixed_step() {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            schedule_label: FixedUpdate.intern(),
+            ..testing::plugin()
+        });
+        app.add_plugins(TimePlugin);
+        app.insert_resource(Time::<Fixed>::from_duration(Duration::from_millis(10)));
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            10,
+        )));
+        let ctx = testing::context(&mut app);
+        // The first update only starts the clock, without running any fixed steps.
+        app.update();
+
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            30,
+        )));
+        let before = ctx.current_tick();
+        app.update();
+        assert_eq!(ctx.current_tick() - before, 3);
+
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        let before = ctx.current_tick();
+        app.update();
+        assert_eq!(ctx.current_tick(), before);
+    }
+
+    /// Sleeps many tasks for different numbers of updates, and checks that each one wakes on its
+    /// target tick without being woken more than once per tick along the way.
+    fn assert_sleepers_wake_on_target_tick(plugin: TokioTasksPlugin) {
+        let mut app = testing::app_with(plugin);
+        let handles: Vec<_> = (0..100)
+            .map(|sleeper| {
+                testing::spawn(&app, move |mut ctx| async move {
+                    let updates = sleeper % 10 + 1;
+                    let start = ctx.current_tick();
+                    let mut polls = 0;
+                    {
+                        let mut sleep = std::pin::pin!(ctx.sleep_updates(updates));
+                        std::future::poll_fn(|cx| {
+                            polls += 1;
+                            sleep.as_mut().poll(cx)
+                        })
+                        .await;
+                    }
+                    (updates, ctx.current_tick() - start, polls)
+                })
+            })
+            .collect();
+        for handle in handles {
+            let (updates, slept, polls) = testing::run_until_finished(&mut app, handle);
+            assert_eq!(slept, updates);
+            // One poll to start sleeping, then at most one wakeup per tick.
+            assert!(
+                polls <= updates + 1,
+                "slept {updates} updates in {polls} polls"
+            );
+        }
+    }
+
+    #[test]
+    fn many_sleepers_wake_on_their_target_tick() {
+        assert_sleepers_wake_on_target_tick(testing::plugin());
+    }
+
+    #[test]
+    fn wakeup_limit_staggers_sleepers_due_on_the_same_tick() {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            max_sleep_wakeups_per_tick: Some(3),
+            ..testing::plugin()
+        });
+        let registered = Arc::new(Mutex::new(Vec::new()));
+        let woken = Arc::new(Mutex::new(Vec::new()));
+        let handles: Vec<_> = (0..10)
+            .map(|sleeper| {
+                let registered = registered.clone();
+                let woken = woken.clone();
+                testing::spawn(&app, move |mut ctx| async move {
+                    let start = ctx.current_tick();
+                    registered.lock().unwrap().push(sleeper);
+                    ctx.sleep_updates(1).await;
+                    woken
+                        .lock()
+                        .unwrap()
+                        .push((sleeper, ctx.current_tick() - start));
+                })
+            })
+            .collect();
+        for handle in handles {
+            testing::run_until_finished(&mut app, handle);
+        }
+        let registered = registered.lock().unwrap().clone();
+        let woken = woken.lock().unwrap().clone();
+        let woken_sleepers: Vec<_> = woken.iter().map(|&(sleeper, _)| sleeper).collect();
+        assert_eq!(woken_sleepers, registered);
+        let slept: Vec<_> = woken.iter().map(|&(_, slept)| slept).collect();
+        assert_eq!(slept, vec![1, 1, 1, 2, 2, 2, 3, 3, 3, 4]);
+    }
+
+    /// Sleeps for the given number of updates straight after a main thread callback returns, and
+    /// returns the tick the callback ran on and the tick the sleep woke on.
+    fn sleep_after_callback(
+        sleep_updates_from: SleepUpdatesFrom,
+        updates: usize,
+    ) -> (usize, usize) {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            sleep_updates_from,
+            ..testing::plugin()
+        });
+        let handle = testing::spawn(&app, move |mut ctx| async move {
+            let callback_tick = ctx.run_on_main_thread(|ctx| ctx.current_tick).await;
+            ctx.sleep_updates(updates).await;
+            (callback_tick, ctx.current_tick())
+        });
+        testing::run_until_finished(&mut app, handle)
+    }
+
+    #[test]
+    fn sleep_after_callback_counts_from_current_tick() {
+        let (callback_tick, woke_tick) = sleep_after_callback(SleepUpdatesFrom::CurrentTick, 0);
+        assert_eq!(woke_tick, callback_tick);
+        let (callback_tick, woke_tick) = sleep_after_callback(SleepUpdatesFrom::CurrentTick, 1);
+        assert_eq!(woke_tick, callback_tick + 1);
+        let (callback_tick, woke_tick) = sleep_after_callback(SleepUpdatesFrom::CurrentTick, 3);
+        assert_eq!(woke_tick, callback_tick + 3);
+    }
+
+    #[test]
+    fn sleep_after_callback_counts_from_next_tick() {
+        let (callback_tick, woke_tick) = sleep_after_callback(SleepUpdatesFrom::NextTick, 0);
+        assert_eq!(woke_tick, callback_tick + 1);
+        let (callback_tick, woke_tick) = sleep_after_callback(SleepUpdatesFrom::NextTick, 1);
+        assert_eq!(woke_tick, callback_tick + 2);
+        let (callback_tick, woke_tick) = sleep_after_callback(SleepUpdatesFrom::NextTick, 3);
+        assert_eq!(woke_tick, callback_tick + 4);
+    }
+
+    /// Applies `items` in chunks, and returns each chunk along with the tick it was applied on,
+    /// followed by the tick on which the call resolved.
+    fn apply_in_chunks(items: Vec<usize>, chunk_size: usize) -> (Vec<(usize, Vec<usize>)>, usize) {
+        let mut app = testing::app();
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let chunks = applied.clone();
+        let handle = testing::spawn(&app, move |mut ctx| async move {
+            ctx.apply_chunked(items, chunk_size, move |chunk, ctx| {
+                chunks.lock().unwrap().push((ctx.current_tick, chunk));
+            })
+            .await;
+            ctx.current_tick()
+        });
+        let resolved_tick = testing::run_until_finished(&mut app, handle);
+        let applied = applied.lock().unwrap().clone();
+        (applied, resolved_tick)
+    }
+
+    #[test]
+    fn apply_chunked_applies_one_chunk_per_tick() {
+        let (applied, resolved_tick) = apply_in_chunks((0..10).collect(), 4);
+        let first_tick = applied[0].0;
+        assert_eq!(
+            applied,
+            vec![
+                (first_tick, vec![0, 1, 2, 3]),
+                (first_tick + 1, vec![4, 5, 6, 7]),
+                (first_tick + 2, vec![8, 9]),
+            ]
+        );
+        assert_eq!(resolved_tick, first_tick + 2);
+    }
+
+    #[test]
+    fn apply_chunked_resolves_on_the_tick_of_an_exact_last_chunk() {
+        let (applied, resolved_tick) = apply_in_chunks((0..8).collect(), 4);
+        let first_tick = applied[0].0;
+        assert_eq!(
+            applied,
+            vec![
+                (first_tick, vec![0, 1, 2, 3]),
+                (first_tick + 1, vec![4, 5, 6, 7])
+            ]
+        );
+        assert_eq!(resolved_tick, first_tick + 1);
+    }
+
+    #[test]
+    fn apply_chunked_treats_a_chunk_size_of_zero_as_one() {
+        let (applied, _) = apply_in_chunks(vec![0, 1], 0);
+        let chunks: Vec<_> = applied.into_iter().map(|(_, chunk)| chunk).collect();
+        assert_eq!(chunks, vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn apply_chunked_never_applies_an_empty_collection() {
+        let (applied, _) = apply_in_chunks(Vec::new(), 4);
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn apply_chunked_stops_once_the_join_is_dropped() {
+        let mut app = testing::app();
+        let applied = Arc::new(AtomicUsize::new(0));
+        let counter = applied.clone();
+        let handle = testing::spawn(&app, move |mut ctx| async move {
+            let join = ctx.apply_chunked((0..100).collect(), 1, move |chunk: Vec<usize>, _| {
+                counter.fetch_add(chunk.len(), Ordering::SeqCst);
+            });
+            ctx.sleep_updates(3).await;
+            drop(join);
+        });
+        testing::run_until_finished(&mut app, handle);
+        let applied_when_dropped = applied.load(Ordering::SeqCst);
+        assert!((1..100).contains(&applied_when_dropped));
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(applied.load(Ordering::SeqCst), applied_when_dropped);
+    }
+
+    #[derive(Resource)]
+    struct Marker;
+
+    #[test]
+    fn aborted_tasks_skip_their_pending_main_thread_callback() {
+        let mut app = testing::app();
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            ctx.run_on_main_thread(|ctx| ctx.world.insert_resource(Marker))
+                .await;
+        });
+        testing::poll_tasks(&app);
+        handle.abort();
+        testing::poll_tasks(&app);
+        app.update();
+        assert!(!app.world().contains_resource::<Marker>());
+
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            ctx.run_on_main_thread(|ctx| ctx.world.insert_resource(Marker))
+                .await;
+        });
+        testing::run_until_finished(&mut app, handle);
+        assert!(app.world().contains_resource::<Marker>());
+    }
+
+    #[derive(Resource)]
+    struct Score(u32);
+
+    #[test]
+    fn world_projection_follows_the_world_until_dropped() {
+        let mut app = testing::app();
+        app.insert_resource(Score(1));
+        let mut ctx = testing::context(&mut app);
+        let mut projection = ctx.world_projection(|world| world.resource::<Score>().0);
+        let latest = |app: &App, projection: &mut WorldProjection<u32>| {
+            let runtime = app.world().resource::<TokioTasksRuntime>().runtime();
+            runtime.block_on(projection.latest())
+        };
+        app.update();
+        assert_eq!(latest(&app, &mut projection), 1);
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert_eq!(runtime.health().tick_callbacks, 1);
+
+        app.insert_resource(Score(2));
+        app.update();
+        assert_eq!(latest(&app, &mut projection), 2);
+
+        drop(projection);
+        app.update();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert_eq!(runtime.health().tick_callbacks, 0);
+    }
+
+    #[test]
+    fn repeating_callbacks_keep_their_state_until_they_break() {
+        let mut app = testing::app();
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            let ticks = ctx
+                .run_on_main_thread_repeating(Vec::new(), |ctx, ticks: &mut Vec<usize>| {
+                    ticks.push(ctx.current_tick);
+                    if ticks.len() == 3 {
+                        ControlFlow::Break(ticks.clone())
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                })
+                .await;
+            (ticks, ctx.current_tick())
+        });
+        let (ticks, resolved_on) = testing::run_until_finished(&mut app, handle);
+        assert_eq!(resolved_on, ticks[2]);
+        assert_eq!(ticks.len(), 3);
+        assert_eq!(ticks[1], ticks[0] + 1);
+        assert_eq!(ticks[2], ticks[0] + 2);
+    }
+
+    #[test]
+    fn spawn_then_passes_the_result_of_the_first_task_on() {
+        let mut app = testing::app();
+        let first = testing::spawn(&app, |_ctx| async { 5 });
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let then = runtime.spawn_then(first, |result, _ctx| async move {
+            result.expect("First task failed") * 2
+        });
+        let then_id = runtime
+            .task_id_for_tokio_task(then.id())
+            .expect("Continuation is not registered");
+        assert!(runtime.tasks().iter().any(|task| task.id == then_id));
+        assert_eq!(testing::run_until_finished(&mut app, then), 10);
+
+        let first = testing::spawn(&app, |_ctx| std::future::pending::<()>());
+        let abort = first.abort_handle();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let then = runtime.spawn_then(first, |result, _ctx| async move {
+            result
+                .expect_err("First task was not aborted")
+                .is_cancelled()
+        });
+        testing::poll_tasks(&app);
+        abort.abort();
+        assert!(testing::run_until_finished(&mut app, then));
+    }
+
+    #[test]
+    fn spawn_into_resource_inserts_the_output_once_the_task_completes() {
+        let mut app = testing::app();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let handle = runtime.spawn_into_resource(|mut ctx| async move {
+            ctx.sleep_updates(2).await;
+            Score(3)
+        });
+        testing::poll_tasks(&app);
+        app.update();
+        assert!(!app.world().contains_resource::<Score>());
+        testing::run_until_finished(&mut app, handle);
+        assert_eq!(app.world().resource::<Score>().0, 3);
+    }
+
+    #[test]
+    fn spawn_into_resource_inserts_nothing_if_the_task_panics() {
+        let mut app = testing::app();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let handle = runtime.spawn_into_resource::<Score, _, _>(|_ctx| async {
+            panic!("Failed to build the score")
+        });
+        for _ in 0..3 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        let runtime = app.world().resource::<TokioTasksRuntime>().runtime();
+        assert!(runtime
+            .block_on(handle)
+            .is_err_and(|error| error.is_panic()));
+        app.update();
+        assert!(!app.world().contains_resource::<Score>());
+    }
+
+    /// The order in which callbacks ran.
+    #[derive(Resource, Default)]
+    struct Ran(Vec<usize>);
+
+    fn enqueue_recorded(ctx: &TaskContext, index: usize) -> MainThreadJoin<usize> {
+        ctx.enqueue_main_thread(move |ctx| {
+            ctx.world.resource_mut::<Ran>().0.push(index);
+            index
+        })
+    }
+
+    #[test]
+    fn enqueued_callbacks_can_be_awaited_in_any_order() {
+        let mut app = testing::app();
+        app.init_resource::<Ran>();
+        let handle = testing::spawn(&app, |ctx| async move {
+            let joins: Vec<_> = (0..3).map(|index| enqueue_recorded(&ctx, index)).collect();
+            let mut outputs = Vec::new();
+            for join in joins.into_iter().rev() {
+                outputs.push(join.await);
+            }
+            outputs
+        });
+        assert_eq!(testing::run_until_finished(&mut app, handle), vec![2, 1, 0]);
+        assert_eq!(app.world().resource::<Ran>().0, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn enqueued_callbacks_still_run_when_their_join_is_dropped() {
+        let mut app = testing::app();
+        app.init_resource::<Ran>();
+        let ctx = testing::context(&mut app);
+        let kept = enqueue_recorded(&ctx, 0);
+        drop(enqueue_recorded(&ctx, 1));
+        drop(enqueue_recorded(&ctx, 2));
+        app.update();
+        assert_eq!(app.world().resource::<Ran>().0, vec![0, 1, 2]);
+        let runtime = app.world().resource::<TokioTasksRuntime>().runtime();
+        assert_eq!(runtime.block_on(kept), 0);
+    }
+
+    #[test]
+    fn run_once_next_tick_applies_its_mutation_on_the_next_update() {
+        let mut app = testing::app();
+        app.update();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        drop(runtime.run_once_next_tick(|world| world.insert_resource(Marker)));
+        assert!(!app.world().contains_resource::<Marker>());
+        app.update();
+        assert!(app.world().contains_resource::<Marker>());
+    }
+
+    #[test]
+    fn try_current_tick_stops_reporting_once_the_app_exits() {
+        let mut app = testing::app();
+        app.update();
+        let ctx = testing::context(&mut app);
+        assert_eq!(ctx.try_current_tick(), Some(ctx.current_tick()));
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        assert_eq!(ctx.try_current_tick(), None);
+    }
+
+    #[test]
+    fn ticks_count_up_from_the_initial_tick() {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            initial_tick: 1000,
+            ..testing::plugin()
+        });
+        let ctx = testing::context(&mut app);
+        assert_eq!(ctx.current_tick(), 1000);
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            ctx.sleep_updates(3).await;
+            ctx.current_tick()
+        });
+        assert_eq!(testing::run_until_finished(&mut app, handle), 1003);
+    }
+
+    #[test]
+    fn wait_for_first_tick_only_waits_before_the_first_update() {
+        let mut app = testing::app();
+        let early = testing::spawn(&app, |mut ctx| async move {
+            ctx.wait_for_first_tick().await;
+            ctx.current_tick()
+        });
+        testing::poll_tasks(&app);
+        assert!(!early.is_finished());
+        app.update();
+        testing::poll_tasks(&app);
+        assert!(early.is_finished());
+        assert_eq!(testing::run_until_finished(&mut app, early), 1);
+
+        app.update();
+        let late = testing::spawn(&app, |mut ctx| async move {
+            ctx.wait_for_first_tick().await;
+            ctx.current_tick()
+        });
+        testing::poll_tasks(&app);
+        assert!(late.is_finished());
+        assert_eq!(testing::run_until_finished(&mut app, late), 2);
+    }
+
+    #[test]
+    fn missing_tick_system_is_not_reported_before_any_task_starts() {
+        let mut check = MissingTickCheck::default();
+        let start = Instant::now();
+        for seconds in [0, 10, 60] {
+            let now = start + Duration::from_secs(seconds);
+            assert!(!check.should_report(false, false, now));
+        }
+        assert!(check.first_task_seen_at.is_none());
+
+        let started = start + Duration::from_secs(60);
+        assert!(!check.should_report(true, false, started));
+        assert!(check.should_report(true, false, started + MISSING_TICK_SYSTEM_DELAY));
+        assert!(!check.should_report(true, false, started + MISSING_TICK_SYSTEM_DELAY * 2));
+    }
+
+    #[test]
+    fn missing_tick_system_is_never_reported_once_ticks_advance() {
+        let mut check = MissingTickCheck::default();
+        let start = Instant::now();
+        assert!(!check.should_report(true, false, start));
+        assert!(!check.should_report(true, true, start + Duration::from_secs(1)));
+        assert!(check.reported);
+        // Even if the tick counter appeared not to have advanced later on, nothing is reported.
+        let late = start + MISSING_TICK_SYSTEM_DELAY * 2;
+        assert!(!check.should_report(true, false, late));
     }
 }
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+
+use crate::{NotifyInfo, TaskContext, TokioTasksRuntime};
+
+/// The named [`Notify`]s used by [`wait_for_notify`](TaskContext::wait_for_notify). Each one is
+/// created the first time its name is used, by either a waiter or a notifier.
+#[derive(Clone, Default)]
+pub(crate) struct NotifyRegistry(Arc<Mutex<HashMap<String, Arc<NamedNotify>>>>);
+
+#[derive(Default)]
+struct NamedNotify {
+    notify: Notify,
+    /// The number of tasks currently waiting in [`wait_for_notify`](TaskContext::wait_for_notify),
+    /// which [`Notify`] does not expose itself.
+    waiters: AtomicUsize,
+}
+
+impl NotifyRegistry {
+    fn get(&self, name: &str) -> Arc<NamedNotify> {
+        let mut notifies = self.lock();
+        if let Some(notify) = notifies.get(name) {
+            return notify.clone();
+        }
+        let notify = Arc::new(NamedNotify::default());
+        notifies.insert(name.to_owned(), notify.clone());
+        notify
+    }
+
+    pub(crate) fn infos(&self) -> Vec<NotifyInfo> {
+        self.lock()
+            .iter()
+            .map(|(name, notify)| NotifyInfo {
+                name: name.clone(),
+                waiters: notify.waiters.load(Ordering::SeqCst),
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<NamedNotify>>> {
+        self.0.lock().expect("Notify registry mutex was poisoned")
+    }
+}
+
+/// Counts a task as waiting on a [`NamedNotify`] until it is dropped, whether the wait finished
+/// or was cancelled.
+struct Waiting<'a>(&'a NamedNotify);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.waiters.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl TaskContext {
+    /// Sleeps the background task until a system wakes it using
+    /// [`notify_one`](TokioTasksRuntime::notify_one) or
+    /// [`notify_waiters`](TokioTasksRuntime::notify_waiters) with the same name. This is more
+    /// efficient than checking for sparse events on every tick, since the task is only woken
+    /// when there is actually something to do.
+    ///
+    /// Names do not need to be registered up front. The semantics are those of Tokio's
+    /// [`Notify`], so a `notify_one` which happens while no task is waiting is remembered and
+    /// wakes the next waiter immediately, whereas `notify_waiters` only wakes tasks which are
+    /// already waiting.
+    pub async fn wait_for_notify(&self, name: &str) {
+        let notify = self.shared.notifies.get(name);
+        notify.waiters.fetch_add(1, Ordering::SeqCst);
+        let _waiting = Waiting(&notify);
+        notify.notify.notified().await;
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Wakes one task waiting in [`wait_for_notify`](TaskContext::wait_for_notify) with the given
+    /// name. If no task is waiting, the next task to wait is woken immediately instead.
+    pub fn notify_one(&self, name: &str) {
+        self.0.shared.notifies.get(name).notify.notify_one();
+    }
+
+    /// Wakes every task which is currently waiting in
+    /// [`wait_for_notify`](TaskContext::wait_for_notify) with the given name. Tasks which start
+    /// waiting afterwards are not affected.
+    pub fn notify_waiters(&self, name: &str) {
+        self.0.shared.notifies.get(name).notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing, TokioTasksRuntime};
+
+    fn waiters(runtime: &TokioTasksRuntime) -> usize {
+        runtime
+            .debug_registries()
+            .notifies
+            .iter()
+            .map(|notify| notify.waiters)
+            .sum()
+    }
+
+    #[test]
+    fn notify_one_wakes_a_waiter_which_arrives_later() {
+        let mut app = testing::app();
+        app.world()
+            .resource::<TokioTasksRuntime>()
+            .notify_one("save");
+        let handle = testing::spawn(&app, |ctx| async move {
+            ctx.wait_for_notify("save").await;
+        });
+        testing::run_until_finished(&mut app, handle);
+        assert_eq!(waiters(app.world().resource::<TokioTasksRuntime>()), 0);
+    }
+
+    #[test]
+    fn notify_waiters_only_wakes_tasks_which_are_already_waiting() {
+        let mut app = testing::app();
+        let early: Vec<_> = (0..2)
+            .map(|_| {
+                testing::spawn(&app, |ctx| async move {
+                    ctx.wait_for_notify("save").await;
+                })
+            })
+            .collect();
+        testing::poll_tasks(&app);
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert_eq!(waiters(runtime), 2);
+        runtime.notify_waiters("save");
+        testing::poll_tasks(&app);
+        assert!(early.iter().all(|handle| handle.is_finished()));
+        assert_eq!(waiters(app.world().resource::<TokioTasksRuntime>()), 0);
+
+        let late = testing::spawn(&app, |ctx| async move {
+            ctx.wait_for_notify("save").await;
+        });
+        for _ in 0..3 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        assert!(!late.is_finished());
+        assert_eq!(waiters(app.world().resource::<TokioTasksRuntime>()), 1);
+        late.abort();
+        testing::poll_tasks(&app);
+        assert_eq!(waiters(app.world().resource::<TokioTasksRuntime>()), 0);
+    }
+}
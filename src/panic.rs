@@ -0,0 +1,113 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::pin;
+use std::task::Poll;
+
+use bevy_utils::tracing;
+
+/// Returns the message of a panic payload, if the panic was raised with a string message.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> Option<&str> {
+    payload
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| payload.downcast_ref::<&'static str>().copied())
+}
+
+/// Polls a future inside a panic boundary, resolving with the panic payload instead of unwinding
+/// if polling the future panics. The future is not polled again after it panics.
+pub(crate) async fn catch_future<F: Future>(future: F) -> Result<F::Output, Box<dyn Any + Send>> {
+    let mut future = pin!(future);
+    std::future::poll_fn(|cx| {
+        // The future is never polled again after panicking, so any broken invariants inside it
+        // cannot be observed.
+        match catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+            Ok(poll) => poll.map(Ok),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    })
+    .await
+}
+
+thread_local! {
+    /// Whether a panic on this thread is about to be caught by [`run_callback`].
+    static CATCHING_CALLBACK_PANICS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Returns whether a panic raised on this thread right now will be caught by [`run_callback`]
+/// rather than unwinding out of the crate.
+pub(crate) fn catching_callback_panics() -> bool {
+    CATCHING_CALLBACK_PANICS.get()
+}
+
+/// Runs a main thread callback. If `catch_panics` is set, a panic in the callback is logged as an
+/// error and [`None`] is returned instead of the panic unwinding through the main thread.
+pub(crate) fn run_callback<Output>(
+    catch_panics: bool,
+    callback: impl FnOnce() -> Output,
+) -> Option<Output> {
+    if !catch_panics {
+        return Some(callback());
+    }
+    // The world is left as the callback left it, which is no worse than what an exclusive system
+    // which panics halfway through would leave.
+    let was_catching = CATCHING_CALLBACK_PANICS.replace(true);
+    let result = catch_unwind(AssertUnwindSafe(callback));
+    CATCHING_CALLBACK_PANICS.set(was_catching);
+    match result {
+        Ok(output) => Some(output),
+        Err(payload) => {
+            tracing::error!(
+                "A main thread callback panicked: {}",
+                panic_message(payload.as_ref()).unwrap_or("<non-string panic payload>")
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::App;
+
+    use crate::{testing, TokioTasksPlugin, TokioTasksRuntime};
+
+    fn panicked_tasks(app: &App) -> usize {
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        runtime.health().panicked_tasks
+    }
+
+    #[test]
+    fn caught_panics_do_not_unwind_through_the_app() {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            catch_panics: true,
+            ..testing::plugin()
+        });
+        let task = testing::spawn(&app, |_ctx| async { panic!("Task panicked") });
+        for _ in 0..2 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        assert!(task.is_finished());
+        assert_eq!(panicked_tasks(&app), 1);
+
+        // A callback is not a task, so its panic is only logged.
+        let ctx = testing::context(&mut app);
+        drop(ctx.enqueue_main_thread(|_| panic!("Callback panicked")));
+        app.update();
+        assert_eq!(panicked_tasks(&app), 1);
+
+        // A task awaiting a callback which panics fails in turn, and is counted.
+        let waiter = testing::spawn(&app, |mut ctx| async move {
+            ctx.run_on_main_thread(|_| panic!("Awaited callback panicked"))
+                .await;
+        });
+        for _ in 0..3 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        assert!(waiter.is_finished());
+        assert_eq!(panicked_tasks(&app), 2);
+    }
+}
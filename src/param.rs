@@ -0,0 +1,137 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::Location;
+
+use bevy_ecs::system::{Resource, SystemParam, SystemParamItem, SystemState};
+use bevy_ecs::world::World;
+
+use crate::{IfAbandoned, TaskContext};
+
+/// The [`SystemState`]s built by [`with_param`](TaskContext::with_param), keyed by the type of
+/// their parameter, so that each parameter type only has to be initialized once.
+#[derive(Resource, Default)]
+struct SystemStateCache(HashMap<TypeId, Box<dyn Any + Send + Sync>>);
+
+/// Takes the cached [`SystemState`] for `P` out of the world, or builds a new one.
+fn take_system_state<P: SystemParam + 'static>(world: &mut World) -> SystemState<P> {
+    world
+        .get_resource_mut::<SystemStateCache>()
+        .and_then(|mut cache| cache.0.remove(&TypeId::of::<P>()))
+        .and_then(|state| state.downcast::<SystemState<P>>().ok())
+        .map(|state| *state)
+        .unwrap_or_else(|| SystemState::new(world))
+}
+
+impl TaskContext {
+    /// Runs a callback on the main thread with access to any [`SystemParam`], such as a
+    /// [`Query`](bevy_ecs::system::Query), a [`Res`](bevy_ecs::system::Res) or
+    /// [`Commands`](bevy_ecs::system::Commands), or a tuple of several of them, and resolves with
+    /// its output. This behaves like [`run_on_main_thread`](Self::run_on_main_thread), but saves
+    /// fetching the parameters from the [`World`] by hand.
+    ///
+    /// ```ignore
+    /// let player_count = ctx
+    ///     .with_param::<Query<(), With<Player>>, _, _>(|players| players.iter().count())
+    ///     .await;
+    /// ```
+    ///
+    /// The [`SystemState`] for each parameter type is built the first time it is used and then
+    /// cached on the main thread, and is shared by every task which uses the same parameter type.
+    /// This means that change detection filters such as
+    /// [`Changed`](bevy_ecs::query::Changed) report changes since the previous `with_param` call
+    /// with the same parameter type from any task, not just from this one.
+    ///
+    /// Deferred operations, such as those queued using [`Commands`](bevy_ecs::system::Commands),
+    /// are applied to the world as soon as the callback returns, before the main thread runs any
+    /// other callback. Their effects are therefore visible to every callback which runs after
+    /// this one, including later callbacks on the same tick.
+    #[track_caller]
+    pub fn with_param<P, Runnable, Output>(
+        &mut self,
+        runnable: Runnable,
+    ) -> impl Future<Output = Output> + Send + '_
+    where
+        P: SystemParam + 'static,
+        Runnable: for<'w, 's> FnOnce(SystemParamItem<'w, 's, P>) -> Output + Send + 'static,
+        Output: Send + 'static,
+    {
+        let location = Location::caller();
+        async move {
+            self.enqueue_at(
+                move |ctx| {
+                    let world = ctx.world;
+                    let mut state = take_system_state::<P>(world);
+                    let output = runnable(state.get_mut(world));
+                    state.apply(world);
+                    world
+                        .get_resource_or_insert_with(SystemStateCache::default)
+                        .0
+                        .insert(TypeId::of::<P>(), Box::new(state));
+                    output
+                },
+                location,
+                IfAbandoned::Skip,
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::App;
+    use bevy_ecs::component::Component;
+    use bevy_ecs::query::Added;
+    use bevy_ecs::system::{Commands, Query};
+    use tokio::task::JoinHandle;
+
+    use super::SystemStateCache;
+    use crate::testing;
+
+    #[derive(Component)]
+    struct Spawned;
+
+    fn spawn_entity(app: &App) -> JoinHandle<()> {
+        testing::spawn(app, |mut ctx| async move {
+            ctx.with_param::<Commands, _, _>(|mut commands| {
+                commands.spawn(Spawned);
+            })
+            .await
+        })
+    }
+
+    /// Counts the entities spawned since the previous count, which relies on the cached
+    /// [`SystemState`](bevy_ecs::system::SystemState) remembering when it last ran.
+    fn count_added(app: &App) -> JoinHandle<usize> {
+        testing::spawn(app, |mut ctx| async move {
+            ctx.with_param::<Query<(), Added<Spawned>>, _, _>(|added| added.iter().count())
+                .await
+        })
+    }
+
+    #[test]
+    fn commands_are_applied_before_the_next_callback_on_the_same_tick() {
+        let mut app = testing::app();
+        let spawner = spawn_entity(&app);
+        let counter = count_added(&app);
+        testing::poll_tasks(&app);
+        app.update();
+        testing::poll_tasks(&app);
+        assert!(spawner.is_finished());
+        assert!(counter.is_finished());
+        assert_eq!(testing::run_until_finished(&mut app, counter), 1);
+    }
+
+    #[test]
+    fn system_states_are_cached_between_calls() {
+        let mut app = testing::app();
+        for _ in 0..2 {
+            let spawner = spawn_entity(&app);
+            testing::run_until_finished(&mut app, spawner);
+            let counter = count_added(&app);
+            assert_eq!(testing::run_until_finished(&mut app, counter), 1);
+        }
+        assert_eq!(app.world().resource::<SystemStateCache>().0.len(), 2);
+    }
+}
@@ -0,0 +1,99 @@
+use std::future::Future;
+
+use bevy_tasks::TaskPool;
+
+/// Where background tasks are polled, configured through
+/// [`executor`](crate::TokioTasksPlugin::executor).
+///
+/// By default tasks run on the Tokio runtime's own worker threads. Since Bevy's task pools
+/// already have a thread per core, running both can oversubscribe the CPU, so tasks can instead
+/// be run on one of Bevy's pools, which reduces the total number of threads. The pool variants
+/// require the `bevy-task-pool` feature.
+///
+/// Tasks on a Bevy pool are still spawned onto the Tokio runtime, but only as a lightweight
+/// wrapper which waits for the pool to finish running them, so their [`JoinHandle`]s and
+/// aborting work as usual. Each poll of a task enters the Tokio runtime, so Tokio's timers and IO
+/// types keep working. This has some limitations:
+///
+/// - Tokio's IO and timer drivers still need a thread to run on, which the runtime provides. When
+///   a Bevy pool is selected, the default [`make_runtime`](crate::TokioTasksPlugin::make_runtime)
+///   builds a multi-threaded runtime with a single worker thread for this. Without the
+///   `rt-multi-thread` feature, the runtime is driven from the main thread once per update tick
+///   instead, so timers and IO are only noticed once per frame.
+/// - Futures spawned directly with [`tokio::spawn`] or [`Handle::spawn`](tokio::runtime::Handle::spawn)
+///   from within a task run on the Tokio runtime rather than the pool. Spawning through the
+///   [`TokioTasksRuntime`](crate::TokioTasksRuntime) or a
+///   [`cheap_spawner`](crate::TaskContext::cheap_spawner) keeps child tasks on the pool.
+/// - Tasks spawned onto an [ordered lane](crate::TokioTasksRuntime::spawn_ordered_task) always
+///   run on the lane's own thread.
+/// - The pool must have been initialized, which Bevy's `TaskPoolPlugin` does as part of its
+///   default and minimal plugins. If it has not been, tasks fall back to running on the Tokio
+///   runtime and a warning is logged.
+///
+/// [`JoinHandle`]: tokio::task::JoinHandle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TaskExecutor {
+    /// Run tasks on the Tokio runtime's worker threads.
+    #[default]
+    Tokio,
+    /// Run tasks on Bevy's [`AsyncComputeTaskPool`](bevy_tasks::AsyncComputeTaskPool).
+    #[cfg(feature = "bevy-task-pool")]
+    AsyncComputeTaskPool,
+    /// Run tasks on Bevy's [`IoTaskPool`](bevy_tasks::IoTaskPool).
+    #[cfg(feature = "bevy-task-pool")]
+    IoTaskPool,
+}
+
+impl TaskExecutor {
+    /// Returns the Bevy pool which tasks should be run on, or [`None`] if they should run on the
+    /// Tokio runtime.
+    #[cfg(feature = "bevy-task-pool")]
+    pub(crate) fn pool(self) -> Option<&'static TaskPool> {
+        let pool = match self {
+            Self::Tokio => return None,
+            Self::AsyncComputeTaskPool => {
+                bevy_tasks::AsyncComputeTaskPool::try_get().map(|pool| &**pool)
+            }
+            Self::IoTaskPool => bevy_tasks::IoTaskPool::try_get().map(|pool| &**pool),
+        };
+        if pool.is_none() {
+            static WARNING: std::sync::Once = std::sync::Once::new();
+            WARNING.call_once(|| {
+                bevy_utils::tracing::warn!(
+                    "TokioTasksPlugin::executor is {self:?}, but that Bevy task pool has not been \
+                    initialized. Background tasks will run on the Tokio runtime instead."
+                );
+            });
+        }
+        pool
+    }
+
+    /// Returns the Bevy pool which tasks should be run on, which is always [`None`] without the
+    /// `bevy-task-pool` feature.
+    #[cfg(not(feature = "bevy-task-pool"))]
+    pub(crate) fn pool(self) -> Option<&'static TaskPool> {
+        None
+    }
+}
+
+/// Runs `future` on `pool`, entering `runtime` around each poll so that Tokio's timers and IO
+/// work on the pool's threads.
+pub(crate) async fn run_on_pool<Task>(
+    pool: &'static TaskPool,
+    runtime: tokio::runtime::Handle,
+    task: Task,
+) -> Task::Output
+where
+    Task: Future + Send + 'static,
+    Task::Output: Send + 'static,
+{
+    pool.spawn(async move {
+        let mut task = std::pin::pin!(task);
+        std::future::poll_fn(|cx| {
+            let _entered = runtime.enter();
+            task.as_mut().poll(cx)
+        })
+        .await
+    })
+    .await
+}
@@ -0,0 +1,27 @@
+use crate::TaskContext;
+
+impl TaskContext {
+    /// Returns whether the main thread is under frame time pressure, meaning that the
+    /// [`frame_time_budget`](crate::TokioTasksPlugin::frame_time_budget) is set and the last
+    /// completed frame took longer than it, as measured by
+    /// [`frame_delta_seconds`](Self::frame_delta_seconds). Tasks doing heavy work can check this
+    /// in their loops and back off while the game is struggling. This is always `false` if no
+    /// budget is set, and before the first tick.
+    pub fn should_yield(&self) -> bool {
+        self.shared
+            .frame_time_budget
+            .is_some_and(|budget| self.frame_delta_seconds() > budget.as_secs_f32())
+    }
+
+    /// Sleeps for one update tick if [`should_yield`](Self::should_yield) returns `true`, and
+    /// returns whether it did. Calling this on each iteration of a heavy loop gives cooperative
+    /// backoff driven by the actual frame times, since the task keeps pausing a tick at a time
+    /// for as long as frames stay over budget.
+    pub async fn yield_if_pressured(&mut self) -> bool {
+        if !self.should_yield() {
+            return false;
+        }
+        self.sleep_updates(1).await;
+        true
+    }
+}
@@ -0,0 +1,99 @@
+use tokio::sync::watch;
+
+use crate::{TaskContext, TaskId, TokioTasksRuntime};
+
+impl TaskContext {
+    /// Reports the progress of this task's long running operation, typically as a fraction between
+    /// `0.0` and `1.0`. Systems and other tasks can observe the reported values using
+    /// [`progress_stream`](TokioTasksRuntime::progress_stream).
+    pub fn report_progress(&self, progress: f32) {
+        if let Some(entry) = self.shared.registry.tasks().get(&self.task_id) {
+            entry.progress_tx.send_replace(Some(progress));
+        }
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Returns a [`ProgressStream`] which observes the progress reported by the given task using
+    /// [`report_progress`](TaskContext::report_progress), or [`None`] if the task has already
+    /// finished.
+    pub fn progress_stream(&self, task_id: TaskId) -> Option<ProgressStream> {
+        self.0
+            .shared
+            .registry
+            .tasks()
+            .get(&task_id)
+            .map(|entry| ProgressStream {
+                progress_rx: entry.progress_tx.subscribe(),
+            })
+    }
+}
+
+/// Observes the progress reported by a single task. Created by
+/// [`progress_stream`](TokioTasksRuntime::progress_stream).
+#[derive(Clone)]
+pub struct ProgressStream {
+    progress_rx: watch::Receiver<Option<f32>>,
+}
+
+impl ProgressStream {
+    /// Returns the most recently reported progress, or [`None`] if the task has not reported any
+    /// progress yet. This never waits, so it can be called from systems every frame. The last
+    /// reported value remains available after the task finishes.
+    pub fn latest(&self) -> Option<f32> {
+        *self.progress_rx.borrow()
+    }
+
+    /// Waits until the task reports new progress and returns it. Returns [`None`] once the task
+    /// has finished and no further progress will be reported.
+    pub async fn next(&mut self) -> Option<f32> {
+        self.progress_rx.changed().await.ok()?;
+        *self.progress_rx.borrow_and_update()
+    }
+
+    /// Returns whether the task which reports to this stream has finished.
+    pub fn is_finished(&self) -> bool {
+        self.progress_rx.has_changed().is_err()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing, TokioTasksRuntime};
+
+    #[test]
+    fn progress_streams_observe_reports_until_the_task_finishes() {
+        let mut app = testing::app();
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            ctx.report_progress(0.25);
+            ctx.sleep_updates(1).await;
+            ctx.report_progress(0.75);
+            ctx.sleep_updates(1).await;
+        });
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let task_id = runtime
+            .task_id_for_tokio_task(handle.id())
+            .expect("Task is not registered");
+        let mut stream = runtime
+            .progress_stream(task_id)
+            .expect("Task has already finished");
+        assert_eq!(stream.latest(), None);
+
+        testing::poll_tasks(&app);
+        let runtime = app.world().resource::<TokioTasksRuntime>().runtime();
+        assert_eq!(stream.latest(), Some(0.25));
+        assert_eq!(runtime.block_on(stream.next()), Some(0.25));
+
+        app.update();
+        testing::poll_tasks(&app);
+        let runtime = app.world().resource::<TokioTasksRuntime>().runtime();
+        assert_eq!(runtime.block_on(stream.next()), Some(0.75));
+
+        testing::run_until_finished(&mut app, handle);
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert!(stream.is_finished());
+        assert_eq!(runtime.runtime().block_on(stream.next()), None);
+        assert_eq!(stream.latest(), Some(0.75));
+        assert!(runtime.progress_stream(task_id).is_none());
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+
+use bevy_utils::{tracing, Duration, Instant};
+
+use crate::{MainThreadCallback, MainThreadFailureKind, TokioTasksRuntime};
+
+/// Selects the data structure which carries one-shot callbacks, such as those queued using
+/// [`run_on_main_thread`](crate::TaskContext::run_on_main_thread), from tasks to the main thread.
+/// The options trade throughput under heavy load against introspection. See
+/// [`callback_queue`](crate::TokioTasksPlugin::callback_queue).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CallbackQueueKind {
+    /// A `Mutex<VecDeque>` which timestamps each callback, allowing
+    /// [`pending_callback_ages`](TokioTasksRuntime::pending_callback_ages) to inspect the queue
+    /// without draining it. Every push and pop takes the lock, which can contend when many threads
+    /// queue callbacks at once. The `callback_queue` benchmark compares the options under load.
+    /// This is the default.
+    #[default]
+    Inspectable,
+    /// An unbounded Tokio mpsc channel, which is lock-free on the sending side but cannot be
+    /// inspected.
+    Unbounded,
+    /// A Tokio mpsc channel with room for the given number of callbacks. Once it is full, further
+    /// attempts to queue callbacks fail with [`MainThreadFailureKind::QueueFull`] until the main
+    /// thread catches up, which turns runaway producers into loud failures instead of unbounded
+    /// memory growth. Like [`Unbounded`](Self::Unbounded), this cannot be inspected. The capacity
+    /// must be greater than zero.
+    Bounded(usize),
+}
+
+/// The order in which [`tick_runtime_update`](crate::tick_runtime_update) runs queued one-shot
+/// callbacks. See [`drain_order`](crate::TokioTasksPlugin::drain_order).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrainOrder {
+    /// Run the oldest callbacks first, in the order they were queued. This is the default.
+    #[default]
+    Fifo,
+    /// Run the newest callbacks first. This suits work where the most recent request is the most
+    /// relevant and older ones are stale, such as UI updates.
+    ///
+    /// Under constant load, or when a [`DrainBudget`](crate::DrainBudget) stops the queue from
+    /// being emptied each tick, the oldest callbacks can be starved and never run, so the tasks
+    /// waiting on them never resume. Only the [`CallbackQueueKind::Inspectable`] queue can be
+    /// drained in this order.
+    Lifo,
+}
+
+impl CallbackQueueKind {
+    pub(crate) fn build(self, drain_order: DrainOrder) -> Arc<dyn MainThreadQueue> {
+        if drain_order == DrainOrder::Lifo && self != Self::Inspectable {
+            tracing::warn!(
+                "TokioTasksPlugin::drain_order is DrainOrder::Lifo, which the {self:?} callback \
+                queue does not support. Callbacks will be run in FIFO order."
+            );
+        }
+        match self {
+            Self::Inspectable => Arc::new(InspectableQueue {
+                callbacks: Mutex::default(),
+                drain_order,
+            }),
+            Self::Unbounded => {
+                let (callback_tx, callback_rx) = tokio::sync::mpsc::unbounded_channel();
+                Arc::new(UnboundedQueue {
+                    callback_tx,
+                    callback_rx: Mutex::new(callback_rx),
+                })
+            }
+            Self::Bounded(capacity) => {
+                let (callback_tx, callback_rx) = tokio::sync::mpsc::channel(capacity);
+                Arc::new(BoundedQueue {
+                    callback_tx,
+                    callback_rx: Mutex::new(callback_rx),
+                })
+            }
+        }
+    }
+}
+
+/// A queue of one-shot callbacks waiting to run on the main thread. Callbacks are pushed from any
+/// thread and popped only by the main thread, which never holds any lock of the queue while a
+/// callback runs, so callbacks are free to queue further callbacks.
+pub(crate) trait MainThreadQueue: Send + Sync + 'static {
+    /// Adds a callback to the back of the queue, or returns why it could not be added.
+    fn push(&self, callback: MainThreadCallback) -> Result<(), MainThreadFailureKind>;
+
+    /// Removes the next callback to run, which is the one at the front of the queue unless it is
+    /// drained in [`DrainOrder::Lifo`] order.
+    fn pop(&self) -> Option<MainThreadCallback>;
+
+    /// Returns how long each queued callback has been waiting, from the front of the queue to the
+    /// back, or [`None`] if the queue does not support inspection.
+    fn ages(&self) -> Option<Vec<Duration>> {
+        None
+    }
+}
+
+/// A callback waiting in an [`InspectableQueue`], along with when it was queued.
+struct QueuedCallback {
+    queued_at: Instant,
+    callback: MainThreadCallback,
+}
+
+/// See [`CallbackQueueKind::Inspectable`].
+struct InspectableQueue {
+    callbacks: Mutex<VecDeque<QueuedCallback>>,
+    drain_order: DrainOrder,
+}
+
+impl InspectableQueue {
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<QueuedCallback>> {
+        self.callbacks
+            .lock()
+            .expect("Main thread callback queue mutex was poisoned")
+    }
+}
+
+impl MainThreadQueue for InspectableQueue {
+    fn push(&self, callback: MainThreadCallback) -> Result<(), MainThreadFailureKind> {
+        self.lock().push_back(QueuedCallback {
+            queued_at: Instant::now(),
+            callback,
+        });
+        Ok(())
+    }
+
+    fn pop(&self) -> Option<MainThreadCallback> {
+        let mut callbacks = self.lock();
+        let queued_callback = match self.drain_order {
+            DrainOrder::Fifo => callbacks.pop_front(),
+            DrainOrder::Lifo => callbacks.pop_back(),
+        };
+        queued_callback.map(|queued_callback| queued_callback.callback)
+    }
+
+    fn ages(&self) -> Option<Vec<Duration>> {
+        let now = Instant::now();
+        Some(
+            self.lock()
+                .iter()
+                .map(|queued_callback| now.saturating_duration_since(queued_callback.queued_at))
+                .collect(),
+        )
+    }
+}
+
+/// See [`CallbackQueueKind::Unbounded`]. The receiver is only ever locked by the main thread.
+struct UnboundedQueue {
+    callback_tx: tokio::sync::mpsc::UnboundedSender<MainThreadCallback>,
+    callback_rx: Mutex<tokio::sync::mpsc::UnboundedReceiver<MainThreadCallback>>,
+}
+
+impl MainThreadQueue for UnboundedQueue {
+    fn push(&self, callback: MainThreadCallback) -> Result<(), MainThreadFailureKind> {
+        self.callback_tx
+            .send(callback)
+            .map_err(|_| MainThreadFailureKind::Enqueue)
+    }
+
+    fn pop(&self) -> Option<MainThreadCallback> {
+        self.callback_rx
+            .lock()
+            .expect("Main thread callback receiver mutex was poisoned")
+            .try_recv()
+            .ok()
+    }
+}
+
+/// See [`CallbackQueueKind::Bounded`]. The receiver is only ever locked by the main thread.
+struct BoundedQueue {
+    callback_tx: tokio::sync::mpsc::Sender<MainThreadCallback>,
+    callback_rx: Mutex<tokio::sync::mpsc::Receiver<MainThreadCallback>>,
+}
+
+impl MainThreadQueue for BoundedQueue {
+    fn push(&self, callback: MainThreadCallback) -> Result<(), MainThreadFailureKind> {
+        self.callback_tx
+            .try_send(callback)
+            .map_err(|error| match error {
+                tokio::sync::mpsc::error::TrySendError::Full(_) => MainThreadFailureKind::QueueFull,
+                tokio::sync::mpsc::error::TrySendError::Closed(_) => MainThreadFailureKind::Enqueue,
+            })
+    }
+
+    fn pop(&self) -> Option<MainThreadCallback> {
+        self.callback_rx
+            .lock()
+            .expect("Main thread callback receiver mutex was poisoned")
+            .try_recv()
+            .ok()
+    }
+}
+
+/// Pushes callbacks onto a [`MainThreadQueue`]. This only holds a weak reference to the queue, so
+/// that like a channel, sending fails once the [`TokioTasksRuntime`] which owns the queue has been
+/// dropped.
+#[derive(Clone)]
+pub(crate) struct CallbackQueueSender(Weak<dyn MainThreadQueue>);
+
+impl CallbackQueueSender {
+    pub(crate) fn new(queue: &Arc<dyn MainThreadQueue>) -> Self {
+        Self(Arc::downgrade(queue))
+    }
+
+    pub(crate) fn send(&self, callback: MainThreadCallback) -> Result<(), MainThreadFailureKind> {
+        match self.0.upgrade() {
+            Some(queue) => queue.push(callback),
+            None => Err(MainThreadFailureKind::Enqueue),
+        }
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Returns how long each callback which is currently queued for the main thread has been
+    /// waiting, ordered from the oldest to the newest. This is intended for debug systems which
+    /// show the distribution of main thread queueing delays. Callbacks which run on every tick,
+    /// such as [world projections](crate::TaskContext::world_projection), are not included.
+    ///
+    /// Returns [`None`] unless the plugin's
+    /// [`callback_queue`](crate::TokioTasksPlugin::callback_queue) is
+    /// [`CallbackQueueKind::Inspectable`]. This briefly blocks tasks from queueing callbacks while
+    /// it walks the queue, so it is best called at most once per frame rather than in a tight
+    /// loop.
+    pub fn pending_callback_ages(&self) -> Option<Vec<Duration>> {
+        self.0.callback_queue.ages()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::App;
+    use bevy_ecs::system::Resource;
+
+    use std::time::Duration;
+
+    use crate::{
+        testing, CallbackQueueKind, DrainBudget, DrainOrder, TaskContext, TokioTasksPlugin,
+        TokioTasksRuntime,
+    };
+
+    /// The order in which callbacks ran.
+    #[derive(Resource, Default)]
+    struct Ran(Vec<usize>);
+
+    fn record(ctx: &TaskContext, index: usize) {
+        drop(ctx.enqueue_main_thread(move |ctx| ctx.world.resource_mut::<Ran>().0.push(index)));
+    }
+
+    fn lifo_app() -> (App, TaskContext) {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            drain_order: DrainOrder::Lifo,
+            ..testing::plugin()
+        });
+        app.init_resource::<Ran>();
+        let ctx = testing::context(&mut app);
+        (app, ctx)
+    }
+
+    #[test]
+    fn lifo_runs_the_newest_callbacks_first() {
+        let (mut app, ctx) = lifo_app();
+        for index in 0..3 {
+            record(&ctx, index);
+        }
+        app.update();
+        assert_eq!(app.world().resource::<Ran>().0, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn lifo_with_a_budget_starves_the_oldest_callbacks() {
+        let (mut app, ctx) = lifo_app();
+        app.insert_resource(DrainBudget {
+            max_callbacks: Some(1),
+            max_duration: None,
+        });
+        for index in 0..3 {
+            record(&ctx, index);
+        }
+        for index in 3..6 {
+            app.update();
+            record(&ctx, index);
+        }
+        app.update();
+        assert_eq!(app.world().resource::<Ran>().0, vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn pending_callback_ages_are_listed_oldest_first() {
+        let mut app = testing::app();
+        app.init_resource::<Ran>();
+        let ctx = testing::context(&mut app);
+        for index in 0..3 {
+            record(&ctx, index);
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let ages = runtime
+            .pending_callback_ages()
+            .expect("The default queue is not inspectable");
+        assert_eq!(ages.len(), 3);
+        assert!(ages[0] >= ages[1] + Duration::from_millis(2));
+        assert!(ages[1] >= ages[2] + Duration::from_millis(2));
+
+        app.update();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert_eq!(runtime.pending_callback_ages(), Some(Vec::new()));
+    }
+
+    #[test]
+    fn pending_callback_ages_are_only_available_for_the_inspectable_queue() {
+        for callback_queue in [CallbackQueueKind::Unbounded, CallbackQueueKind::Bounded(4)] {
+            let mut app = testing::app_with(TokioTasksPlugin {
+                callback_queue,
+                ..testing::plugin()
+            });
+            app.init_resource::<Ran>();
+            let ctx = testing::context(&mut app);
+            record(&ctx, 0);
+            let runtime = app.world().resource::<TokioTasksRuntime>();
+            assert_eq!(runtime.pending_callback_ages(), None);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::TokioTasksRuntime;
+
+/// A snapshot of the named coordination primitives which tasks share, returned by
+/// [`debug_registries`](TokioTasksRuntime::debug_registries). Each list is ordered by name. Enable
+/// the `serde` feature to make it serializable.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RegistriesSnapshot {
+    /// The semaphores configured through [`semaphores`](crate::TokioTasksPlugin::semaphores).
+    pub semaphores: Vec<SemaphoreInfo>,
+    /// The notifies which have been used by
+    /// [`wait_for_notify`](crate::TaskContext::wait_for_notify) or one of the runtime's notify
+    /// functions.
+    pub notifies: Vec<NotifyInfo>,
+    /// The channels which have been used by [`request`](TokioTasksRuntime::request) or
+    /// [`subscribe_requests`](crate::TaskContext::subscribe_requests).
+    pub request_channels: Vec<RequestChannelInfo>,
+    /// The lanes which have been used by
+    /// [`spawn_ordered_task`](TokioTasksRuntime::spawn_ordered_task). This is always empty on the
+    /// wasm32 architecture, where lanes are not available.
+    pub ordered_lanes: Vec<OrderedLaneInfo>,
+}
+
+/// The state of a named semaphore. Part of the [`RegistriesSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SemaphoreInfo {
+    /// The name the semaphore was configured with.
+    pub name: String,
+    /// The number of permits which can currently be acquired without waiting.
+    pub available_permits: usize,
+}
+
+/// The state of a named notify. Part of the [`RegistriesSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NotifyInfo {
+    /// The name of the notify.
+    pub name: String,
+    /// The number of tasks currently waiting in
+    /// [`wait_for_notify`](crate::TaskContext::wait_for_notify).
+    pub waiters: usize,
+}
+
+/// The state of a request channel. Part of the [`RegistriesSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RequestChannelInfo {
+    /// The name of the channel's request type.
+    pub request_type: &'static str,
+    /// The name of the channel's response type.
+    pub response_type: &'static str,
+    /// The number of requests which have been sent but not yet received by a task.
+    pub queued_requests: usize,
+    /// The number of [`RequestReceiver`](crate::RequestReceiver)s which are subscribed to the
+    /// channel.
+    pub subscribers: usize,
+}
+
+/// The state of an ordered lane. Part of the [`RegistriesSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OrderedLaneInfo {
+    /// The name of the lane.
+    pub name: String,
+    /// The number of tasks in the lane which have not finished yet, including the one which is
+    /// running.
+    pub pending_tasks: usize,
+}
+
+impl TokioTasksRuntime {
+    /// Returns a snapshot of the named coordination primitives which tasks share, and basic stats
+    /// about each, for seeing why tasks are blocked on one another. This complements
+    /// [`tasks`](Self::tasks), which lists the tasks themselves.
+    pub fn debug_registries(&self) -> RegistriesSnapshot {
+        let mut snapshot = RegistriesSnapshot {
+            semaphores: self.0.shared.semaphores.infos(),
+            notifies: self.0.shared.notifies.infos(),
+            request_channels: self.0.shared.request_channels.infos(),
+            #[cfg(not(target_arch = "wasm32"))]
+            ordered_lanes: self.0.ordered_lanes.infos(),
+            #[cfg(target_arch = "wasm32")]
+            ordered_lanes: Vec::new(),
+        };
+        snapshot
+            .semaphores
+            .sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        snapshot
+            .notifies
+            .sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        snapshot.request_channels.sort_unstable_by(|a, b| {
+            (a.request_type, a.response_type).cmp(&(b.request_type, b.response_type))
+        });
+        snapshot
+            .ordered_lanes
+            .sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        snapshot
+    }
+}
@@ -0,0 +1,985 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, TryLockError};
+
+use bevy_ecs::system::Resource;
+use bevy_utils::{tracing, Duration, Instant};
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::{Categories, Category, SpawnLatency, TaskContext, TaskExecutor, TokioTasksRuntime};
+
+/// A unique identifier for a background task spawned onto the [`TokioTasksRuntime`]. Identifiers
+/// are allocated when a task is spawned or [reserved](TokioTasksRuntime::reserve_task) and are
+/// never reused for the lifetime of the runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TaskId(u64);
+
+impl TaskId {
+    /// Returns the raw numeric value of this identifier.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for TaskId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "task #{}", self.0)
+    }
+}
+
+/// Whether a registered task has actually started running yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TaskState {
+    /// The task's id has been reserved using [`reserve_task`](TokioTasksRuntime::reserve_task)
+    /// but its future has not been spawned yet.
+    Reserved,
+    /// The task's future has been spawned onto the runtime.
+    Running,
+}
+
+/// The bookkeeping which the registry stores for each live task.
+pub(crate) struct TaskEntry {
+    pub(crate) state: TaskState,
+    pub(crate) name: Option<Arc<str>>,
+    /// The latest value reported using [`report_progress`](TaskContext::report_progress).
+    pub(crate) progress_tx: tokio::sync::watch::Sender<Option<f32>>,
+    /// When the task started running, or [`None`] if it is only reserved.
+    started_at: Option<Instant>,
+    /// Where the task was spawned or reserved from.
+    spawned_at: &'static Location<'static>,
+    /// Aborts the task. This is set through the task's [`TaskRegistration`] just after the task is
+    /// spawned, without locking the registry again, so a task which has only just started may not
+    /// have one yet.
+    abort_handle: Arc<OnceLock<AbortHandle>>,
+    /// The total time the task's main thread callbacks have taken to run.
+    main_thread_cost: Duration,
+    /// The buffer of the task's captured tracing events, if it is capturing them.
+    #[cfg(feature = "task-logs")]
+    pub(crate) logs: Option<Arc<crate::task_log::TaskLogBuffer>>,
+}
+
+impl TaskEntry {
+    /// Returns the id which Tokio assigned to the task, once it has been spawned.
+    fn tokio_id(&self) -> Option<tokio::task::Id> {
+        self.abort_handle.get().map(AbortHandle::id)
+    }
+}
+
+/// Tracks every task which has been spawned or reserved and has not yet finished.
+#[derive(Default)]
+pub(crate) struct TaskRegistry {
+    next_id: AtomicU64,
+    tasks: Mutex<HashMap<TaskId, TaskEntry>>,
+    /// The number of entries in `tasks` which are [`TaskState::Running`], kept separately so it
+    /// can be read every tick without locking the registry.
+    live_tasks: AtomicUsize,
+    /// Whether any task has ever been started, as opposed to only reserved.
+    started_any: AtomicBool,
+    /// See [`is_draining`](TokioTasksRuntime::is_draining).
+    draining: AtomicBool,
+    /// The number of tasks which have finished by panicking.
+    panicked_tasks: AtomicUsize,
+    /// The most recent tasks to panic, oldest first, capped at [`RECENT_PANICS`] entries.
+    recent_panics: Mutex<VecDeque<PanickedTask>>,
+    /// See [`catch_panics`](crate::TokioTasksPlugin::catch_panics).
+    catch_panics: bool,
+    /// See [`executor`](crate::TokioTasksPlugin::executor).
+    executor: TaskExecutor,
+    /// The number of tasks which have been polled, and the total and longest spawn latencies
+    /// among them in nanoseconds. See [`SpawnLatency`].
+    polled_tasks: AtomicU64,
+    total_spawn_latency_nanos: AtomicU64,
+    max_spawn_latency_nanos: AtomicU64,
+}
+
+/// How many panicked tasks [`recent_panics`](TokioTasksRuntime::recent_panics) remembers.
+const RECENT_PANICS: usize = 16;
+
+/// How long ago a panic can have happened to count towards
+/// [`HealthSnapshot::recent_panics`](crate::HealthSnapshot::recent_panics).
+const RECENT_PANIC_WINDOW: Duration = Duration::from_secs(60);
+
+impl TaskRegistry {
+    pub(crate) fn new(catch_panics: bool, executor: TaskExecutor) -> Self {
+        Self {
+            catch_panics,
+            executor,
+            ..Self::default()
+        }
+    }
+
+    /// Returns whether panics should be caught and logged rather than left to unwind.
+    pub(crate) fn catch_panics(&self) -> bool {
+        self.catch_panics
+    }
+
+    pub(crate) fn tasks(&self) -> MutexGuard<'_, HashMap<TaskId, TaskEntry>> {
+        self.tasks.lock().expect("Task registry mutex was poisoned")
+    }
+
+    /// Like [`TokioTasksRuntime::tasks`], but returns [`None`] instead of blocking if the registry
+    /// is locked, which it may be by the current thread when called from a panic hook.
+    pub(crate) fn try_task_infos(&self) -> Option<Vec<TaskInfo>> {
+        match self.tasks.try_lock() {
+            Ok(tasks) => Some(task_infos(&tasks)),
+            Err(TryLockError::Poisoned(poisoned)) => Some(task_infos(&poisoned.into_inner())),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    /// Returns the number of tasks which have been spawned and have not finished yet.
+    pub(crate) fn live_task_count(&self) -> usize {
+        self.live_tasks.load(Ordering::SeqCst)
+    }
+
+    /// Stops any more tasks from being spawned. See
+    /// [`is_draining`](TokioTasksRuntime::is_draining).
+    pub(crate) fn begin_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether any task has ever been started on this registry.
+    pub(crate) fn started_any(&self) -> bool {
+        self.started_any.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of tasks which have finished by panicking since the runtime was created.
+    pub(crate) fn panicked_task_count(&self) -> usize {
+        self.panicked_tasks.load(Ordering::SeqCst)
+    }
+
+    /// Adds the running time of one of a task's main thread callbacks to the task's total. Nothing
+    /// is recorded for tasks which have already finished.
+    pub(crate) fn record_main_thread_cost(&self, id: TaskId, elapsed: Duration) {
+        if let Some(entry) = self.tasks().get_mut(&id) {
+            entry.main_thread_cost += elapsed;
+        }
+    }
+
+    /// Records how long a task waited between being spawned and first being polled.
+    fn record_spawn_latency(&self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.total_spawn_latency_nanos
+            .fetch_add(nanos, Ordering::Relaxed);
+        self.max_spawn_latency_nanos
+            .fetch_max(nanos, Ordering::Relaxed);
+        self.polled_tasks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn spawn_latency(&self) -> SpawnLatency {
+        let polled_tasks = self.polled_tasks.load(Ordering::Relaxed);
+        if polled_tasks == 0 {
+            return SpawnLatency::default();
+        }
+        let total = self.total_spawn_latency_nanos.load(Ordering::Relaxed);
+        SpawnLatency {
+            polled_tasks,
+            average: Some(Duration::from_nanos(total / polled_tasks)),
+            max: Some(Duration::from_nanos(
+                self.max_spawn_latency_nanos.load(Ordering::Relaxed),
+            )),
+        }
+    }
+
+    /// Returns how many of the remembered panics happened within the last
+    /// [`RECENT_PANIC_WINDOW`].
+    pub(crate) fn recent_panic_count(&self) -> usize {
+        count_within_window(&self.recent_panics())
+    }
+
+    /// Like [`recent_panic_count`](Self::recent_panic_count), but returns [`None`] instead of
+    /// blocking if another thread is recording a panic.
+    pub(crate) fn try_recent_panic_count(&self) -> Option<usize> {
+        match self.recent_panics.try_lock() {
+            Ok(recent_panics) => Some(count_within_window(&recent_panics)),
+            Err(TryLockError::Poisoned(poisoned)) => {
+                Some(count_within_window(&poisoned.into_inner()))
+            }
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    fn recent_panics(&self) -> MutexGuard<'_, VecDeque<PanickedTask>> {
+        self.recent_panics
+            .lock()
+            .expect("Recent panics mutex was poisoned")
+    }
+
+    /// Counts a task as having panicked and remembers it as one of the recent panics.
+    fn record_panic(&self, id: TaskId, name: Option<Arc<str>>) {
+        self.panicked_tasks.fetch_add(1, Ordering::SeqCst);
+        let mut recent_panics = self.recent_panics();
+        if recent_panics.len() == RECENT_PANICS {
+            recent_panics.pop_front();
+        }
+        recent_panics.push_back(PanickedTask {
+            id,
+            name,
+            panicked_at: Instant::now(),
+        });
+    }
+
+    /// Registers a batch of tasks which start running straight away, taking the registry lock
+    /// only once for the whole batch.
+    fn register_running(
+        self: &Arc<Self>,
+        count: usize,
+        spawned_at: &'static Location<'static>,
+    ) -> Vec<TaskRegistration> {
+        let first_id = self.next_id.fetch_add(count as u64, Ordering::Relaxed);
+        let started_at = Instant::now();
+        let mut tasks = self.tasks();
+        tasks.reserve(count);
+        let registrations = (first_id..first_id + count as u64)
+            .map(|raw_id| {
+                let id = TaskId(raw_id);
+                let abort_handle = Arc::<OnceLock<AbortHandle>>::default();
+                tasks.insert(
+                    id,
+                    TaskEntry {
+                        state: TaskState::Running,
+                        name: None,
+                        progress_tx: tokio::sync::watch::Sender::new(None),
+                        started_at: Some(started_at),
+                        spawned_at,
+                        abort_handle: abort_handle.clone(),
+                        main_thread_cost: Duration::ZERO,
+                        #[cfg(feature = "task-logs")]
+                        logs: None,
+                    },
+                );
+                TaskRegistration {
+                    id,
+                    registry: self.clone(),
+                    abort_handle,
+                }
+            })
+            .collect();
+        // Counted while still holding the lock, so that no registration can be dropped and
+        // uncounted before it was counted.
+        self.live_tasks.fetch_add(count, Ordering::SeqCst);
+        if count > 0 {
+            self.started_any.store(true, Ordering::SeqCst);
+        }
+        registrations
+    }
+
+    /// Allocates a new id and registers it, returning a registration which removes the entry
+    /// again once dropped.
+    pub(crate) fn register(
+        self: &Arc<Self>,
+        spawned_at: &'static Location<'static>,
+    ) -> TaskRegistration {
+        let id = TaskId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let abort_handle = Arc::<OnceLock<AbortHandle>>::default();
+        self.tasks().insert(
+            id,
+            TaskEntry {
+                state: TaskState::Reserved,
+                name: None,
+                progress_tx: tokio::sync::watch::Sender::new(None),
+                started_at: None,
+                spawned_at,
+                abort_handle: abort_handle.clone(),
+                main_thread_cost: Duration::ZERO,
+                #[cfg(feature = "task-logs")]
+                logs: None,
+            },
+        );
+        TaskRegistration {
+            id,
+            registry: self.clone(),
+            abort_handle,
+        }
+    }
+}
+
+/// Keeps a task's entry in the [`TaskRegistry`] alive. The entry is removed when this is dropped,
+/// which happens when the task's future completes, panics or is aborted, or when a [`TaskSlot`]
+/// is dropped without being started.
+pub(crate) struct TaskRegistration {
+    id: TaskId,
+    registry: Arc<TaskRegistry>,
+    /// Shared with the task's entry, so that the task's abort handle can be recorded once it is
+    /// spawned without locking the registry.
+    abort_handle: Arc<OnceLock<AbortHandle>>,
+}
+
+impl TaskRegistration {
+    pub(crate) fn id(&self) -> TaskId {
+        self.id
+    }
+
+    pub(crate) fn set_state(&self, state: TaskState) {
+        if let Some(entry) = self.registry.tasks().get_mut(&self.id) {
+            if entry.state != TaskState::Running && state == TaskState::Running {
+                self.registry.live_tasks.fetch_add(1, Ordering::SeqCst);
+                self.registry.started_any.store(true, Ordering::SeqCst);
+                entry.started_at = Some(Instant::now());
+            }
+            entry.state = state;
+        }
+    }
+
+    pub(crate) fn set_name(&self, name: Arc<str>) {
+        if let Some(entry) = self.registry.tasks().get_mut(&self.id) {
+            entry.name = Some(name);
+        }
+    }
+}
+
+impl Drop for TaskRegistration {
+    fn drop(&mut self) {
+        let entry = self.registry.tasks().remove(&self.id);
+        let Some(entry) = entry.filter(|entry| entry.state == TaskState::Running) else {
+            return;
+        };
+        self.registry.live_tasks.fetch_sub(1, Ordering::SeqCst);
+        // Registrations of running tasks are owned by the task's future, so this only happens
+        // during a panic if the future itself is unwinding.
+        if std::thread::panicking() {
+            self.registry.record_panic(self.id, entry.name);
+        }
+    }
+}
+
+/// A registered [`TaskId`] which has not been started yet, created by
+/// [`reserve_task`](TokioTasksRuntime::reserve_task). Call [`start`](TaskSlot::start) to spawn
+/// the task into this slot. If the slot is dropped without being started, its registry entry is
+/// removed.
+pub struct TaskSlot {
+    registration: TaskRegistration,
+    pub(crate) context: TaskContext,
+    pub(crate) categories: Categories,
+    runtime: tokio::runtime::Handle,
+    spawned_at: &'static Location<'static>,
+    executor: TaskExecutor,
+    #[cfg(feature = "task-logs")]
+    pub(crate) log_capture: crate::LogCapture,
+}
+
+impl TaskSlot {
+    /// Returns the id which the task will have once it is started.
+    pub fn id(&self) -> TaskId {
+        self.registration.id()
+    }
+
+    /// Gives the task a human readable name, in the same way as
+    /// [`spawn_named_background_task`](TokioTasksRuntime::spawn_named_background_task).
+    pub fn with_name(mut self, name: impl Into<Arc<str>>) -> Self {
+        let name = name.into();
+        self.registration.set_name(name.clone());
+        self.context.task_name = Some(name);
+        self
+    }
+
+    /// Makes the task run on the given runtime instead of the plugin's runtime, and on that
+    /// runtime's own threads even if the plugin's [`TaskExecutor`] is a Bevy task pool.
+    pub(crate) fn on_runtime(mut self, runtime: tokio::runtime::Handle) -> Self {
+        self.runtime = runtime;
+        self.executor = TaskExecutor::Tokio;
+        self
+    }
+
+    /// Spawns a task into this slot. This behaves exactly like
+    /// [`spawn_background_task`](TokioTasksRuntime::spawn_background_task), except that the
+    /// task's id was already known before it started.
+    pub fn start<Task, Output, Spawnable>(self, spawnable_task: Spawnable) -> JoinHandle<Output>
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        let Self {
+            registration,
+            context,
+            runtime,
+            spawned_at,
+            executor,
+            #[cfg(feature = "task-logs")]
+            log_capture,
+            ..
+        } = self;
+        registration.set_state(TaskState::Running);
+        let id = registration.id();
+        tracing::debug!(
+            task_id = id.0,
+            task_name = context.task_name.as_deref(),
+            spawned_at = %spawned_at,
+            "Spawned background task"
+        );
+        #[cfg(feature = "task-logs")]
+        if log_capture.applies(context.task_name.is_some()) {
+            let buffer = registration.registry.start_log_capture(id);
+            return spawn_registered(&runtime, executor, registration, context, move |ctx| {
+                buffer.instrument(id, spawnable_task(ctx))
+            });
+        }
+        spawn_registered(&runtime, executor, registration, context, spawnable_task)
+    }
+}
+
+/// Spawns child background tasks from within a task, created by
+/// [`cheap_spawner`](TaskContext::cheap_spawner). Cloning a spawner only clones an [`Arc`], so it
+/// can be handed to every iteration of a hot producer loop. Children are tracked in the task
+/// registry like any other background task.
+#[derive(Clone)]
+pub struct TaskSpawner(Arc<TaskSpawnerInner>);
+
+struct TaskSpawnerInner {
+    /// The context which each child's context is cloned from, once its id is known.
+    template: TaskContext,
+    runtime: tokio::runtime::Handle,
+}
+
+impl TaskSpawner {
+    /// Spawns a child task, which behaves like one spawned with
+    /// [`spawn_background_task`](TokioTasksRuntime::spawn_background_task).
+    #[track_caller]
+    pub fn spawn<Task, Output, Spawnable>(&self, spawnable_task: Spawnable) -> JoinHandle<Output>
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        let TaskSpawnerInner { template, runtime } = &*self.0;
+        let spawned_at = Location::caller();
+        let registration = template.shared.registry.register(spawned_at);
+        registration.set_state(TaskState::Running);
+        let id = registration.id();
+        tracing::debug!(
+            task_id = id.0,
+            spawned_at = %spawned_at,
+            "Spawned background task"
+        );
+        let mut context = template.clone();
+        context.task_id = id;
+        let executor = template.shared.registry.executor;
+        spawn_registered(runtime, executor, registration, context, spawnable_task)
+    }
+}
+
+impl TaskContext {
+    /// Returns a [`TaskSpawner`] for spawning child tasks from this task. Each child's context
+    /// shares this context's state through a single [`Arc`], so spawning a child does not copy
+    /// any of it.
+    ///
+    /// Children are spawned onto the runtime the calling task is running on. Like tasks spawned
+    /// from the [`TokioTasksRuntime`], they have no name, category or cancellation token, even if
+    /// this task does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a Tokio runtime.
+    pub fn cheap_spawner(&self) -> TaskSpawner {
+        let mut template = self.clone();
+        template.task_name = None;
+        template.cancellation_token = None;
+        template.category = None;
+        TaskSpawner(Arc::new(TaskSpawnerInner {
+            template,
+            runtime: tokio::runtime::Handle::current(),
+        }))
+    }
+}
+
+/// Spawns a task whose registration is already [`TaskState::Running`], and records the task's
+/// abort handle through its registration.
+fn spawn_registered<Task, Output, Spawnable>(
+    runtime: &tokio::runtime::Handle,
+    executor: TaskExecutor,
+    registration: TaskRegistration,
+    context: TaskContext,
+    spawnable_task: Spawnable,
+) -> JoinHandle<Output>
+where
+    Task: Future<Output = Output> + Send + 'static,
+    Output: Send + 'static,
+    Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+{
+    let id = registration.id();
+    if registration.registry.is_draining() {
+        tracing::debug!(
+            task_id = id.0,
+            "Refused to spawn background task because the runtime is draining"
+        );
+        // Dropping the registration removes the task again, and the aborted handle resolves
+        // with a cancellation error without the spawnable ever being called.
+        drop(registration);
+        let handle = runtime.spawn(std::future::pending());
+        handle.abort();
+        return handle;
+    }
+    let live_in_category = context.category.as_ref().map(Category::live_task);
+    let task = spawnable_task(context);
+    let registry = registration.registry.clone();
+    let spawned_at = Instant::now();
+    let future = async move {
+        registry.record_spawn_latency(spawned_at.elapsed());
+        let _live_in_category = live_in_category;
+        task.await
+    };
+    let pool = executor.pool().map(|pool| (pool, runtime.clone()));
+    let future = async move {
+        match pool {
+            Some((pool, runtime)) => crate::pool::run_on_pool(pool, runtime, future).await,
+            None => future.await,
+        }
+    };
+    let abort_handle = registration.abort_handle.clone();
+    if !registration.registry.catch_panics() {
+        let handle = runtime.spawn(async move {
+            let _registration = registration;
+            future.await
+        });
+        let _ = abort_handle.set(handle.abort_handle());
+        return handle;
+    }
+    let (abort_tx, abort_rx) = tokio::sync::oneshot::channel::<AbortHandle>();
+    let handle = runtime.spawn(async move {
+        let registration = registration;
+        match crate::panic::catch_future(future).await {
+            Ok(output) => output,
+            Err(payload) => {
+                let registry = &registration.registry;
+                let name = registry
+                    .tasks()
+                    .get(&id)
+                    .and_then(|entry| entry.name.clone());
+                tracing::error!(
+                    "{id}{} panicked: {}",
+                    name.as_deref()
+                        .map(|name| format!(" \"{name}\""))
+                        .unwrap_or_default(),
+                    crate::panic::panic_message(payload.as_ref())
+                        .unwrap_or("<non-string panic payload>")
+                );
+                registry.record_panic(id, name);
+                // There is no output to finish with, so the task cancels itself instead. The
+                // abort only takes effect once this future yields.
+                if let Ok(abort_handle) = abort_rx.await {
+                    abort_handle.abort();
+                }
+                std::future::pending().await
+            }
+        }
+    });
+    let _ = abort_tx.send(handle.abort_handle());
+    let _ = abort_handle.set(handle.abort_handle());
+    handle
+}
+
+/// A Bevy [`Resource`] holding the number of background tasks which are currently running, updated
+/// by [`tick_runtime_update`](crate::tick_runtime_update). The resource is only marked as changed
+/// on ticks where the count actually changes, so systems reacting to its changes only run when
+/// tasks start or finish.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LiveTaskCount(pub usize);
+
+/// A snapshot of a task which is registered with the [`TokioTasksRuntime`], returned by
+/// [`tasks`](TokioTasksRuntime::tasks).
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TaskInfo {
+    /// The task's id.
+    pub id: TaskId,
+    /// The task's name, if it has one.
+    pub name: Option<Arc<str>>,
+    /// How long the task has been running for, or [`None`] if it has only been
+    /// [reserved](TokioTasksRuntime::reserve_task) and not started yet.
+    pub age: Option<Duration>,
+    /// The id which Tokio itself assigned to the task, as reported by tools such as
+    /// `tokio-console`, or [`None`] if the task has not been spawned onto the runtime yet. This is
+    /// not serialized, since Tokio's ids do not support it.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub tokio_id: Option<tokio::task::Id>,
+    /// Where the task was spawned or reserved from, which helps track down leaked or runaway
+    /// tasks. This is serialized as a `file:line:column` string.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_location"))]
+    pub spawned_at: &'static Location<'static>,
+}
+
+#[cfg(feature = "serde")]
+fn serialize_location<S: serde::Serializer>(
+    location: &&'static Location<'static>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(location)
+}
+
+/// A task which finished by panicking, returned by
+/// [`recent_panics`](TokioTasksRuntime::recent_panics).
+#[derive(Clone, Debug)]
+pub struct PanickedTask {
+    /// The id of the task which panicked.
+    pub id: TaskId,
+    /// The name of the task which panicked, if it had one.
+    pub name: Option<Arc<str>>,
+    /// When the task panicked.
+    pub panicked_at: Instant,
+}
+
+/// Counts the panics which happened within the last [`RECENT_PANIC_WINDOW`].
+fn count_within_window(recent_panics: &VecDeque<PanickedTask>) -> usize {
+    recent_panics
+        .iter()
+        .filter(|panicked| panicked.panicked_at.elapsed() <= RECENT_PANIC_WINDOW)
+        .count()
+}
+
+/// Returns a snapshot of each of the registered tasks, ordered by id.
+fn task_infos(tasks: &HashMap<TaskId, TaskEntry>) -> Vec<TaskInfo> {
+    let now = Instant::now();
+    let mut tasks: Vec<_> = tasks
+        .iter()
+        .map(|(&id, entry)| TaskInfo {
+            id,
+            name: entry.name.clone(),
+            age: entry
+                .started_at
+                .map(|started_at| now.saturating_duration_since(started_at)),
+            tokio_id: entry.tokio_id(),
+            spawned_at: entry.spawned_at,
+        })
+        .collect();
+    tasks.sort_unstable_by_key(|task| task.id);
+    tasks
+}
+
+impl TokioTasksRuntime {
+    /// Returns a snapshot of every task which has been spawned or reserved and has not finished
+    /// yet, ordered by id.
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        task_infos(&self.0.shared.registry.tasks())
+    }
+
+    /// Spawn many tasks at once, in the same way as calling
+    /// [`spawn_background_task`](Self::spawn_background_task) for each of them but with less
+    /// overhead per task, since the whole batch is registered while taking the task registry's
+    /// lock only once. This is intended for spawning large numbers of small tasks, such as one per
+    /// grid cell. The join handles are returned in the same order as the spawnables.
+    #[track_caller]
+    pub fn spawn_background_tasks<Spawnables, Task, Output, Spawnable>(
+        &self,
+        spawnable_tasks: Spawnables,
+    ) -> Vec<JoinHandle<Output>>
+    where
+        Spawnables: IntoIterator<Item = Spawnable>,
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        let spawnable_tasks: Vec<_> = spawnable_tasks.into_iter().collect();
+        let registry = &self.0.shared.registry;
+        let spawned_at = Location::caller();
+        let registrations = registry.register_running(spawnable_tasks.len(), spawned_at);
+        let Some(first) = registrations.first() else {
+            return Vec::new();
+        };
+        tracing::debug!(
+            first_task_id = first.id().0,
+            count = registrations.len(),
+            spawned_at = %spawned_at,
+            "Spawned batch of background tasks"
+        );
+        let context = self.new_task_context(first.id());
+        let runtime = self.0.runtime.handle();
+        let executor = registry.executor;
+        registrations
+            .into_iter()
+            .zip(spawnable_tasks)
+            .map(|(registration, spawnable_task)| {
+                let mut context = context.clone();
+                context.task_id = registration.id();
+                spawn_registered(runtime, executor, registration, context, spawnable_task)
+            })
+            .collect()
+    }
+
+    /// Aborts the running task with the given id, in the same way as calling
+    /// [`JoinHandle::abort`] on its handle. Returns whether a running task with the id was found.
+    pub fn abort_task(&self, id: TaskId) -> bool {
+        let abort_handle = self
+            .0
+            .shared
+            .registry
+            .tasks()
+            .get(&id)
+            .and_then(|entry| entry.abort_handle.get().cloned());
+        match abort_handle {
+            Some(abort_handle) => {
+                abort_handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the total time which the main thread has spent running callbacks requested by the
+    /// task with the given id, such as those from
+    /// [`run_on_main_thread`](TaskContext::run_on_main_thread), or [`None`] if no such task is
+    /// running. This attributes main thread cost to the tasks which cause it, to find which task
+    /// is hurting the frame time.
+    ///
+    /// Tasks are forgotten as soon as they finish, so callbacks which run after their task has
+    /// finished are not counted.
+    pub fn task_main_thread_cost(&self, id: TaskId) -> Option<Duration> {
+        Some(self.0.shared.registry.tasks().get(&id)?.main_thread_cost)
+    }
+
+    /// Returns up to `n` running tasks with the highest
+    /// [`task_main_thread_cost`](Self::task_main_thread_cost), most expensive first. Tasks
+    /// whose callbacks have not run yet are not included.
+    pub fn top_main_thread_consumers(&self, n: usize) -> Vec<(TaskId, Duration)> {
+        let mut consumers: Vec<_> = self
+            .0
+            .shared
+            .registry
+            .tasks()
+            .iter()
+            .filter(|(_, entry)| !entry.main_thread_cost.is_zero())
+            .map(|(&id, entry)| (id, entry.main_thread_cost))
+            .collect();
+        consumers.sort_unstable_by(|(a_id, a_cost), (b_id, b_cost)| {
+            b_cost.cmp(a_cost).then(a_id.cmp(b_id))
+        });
+        consumers.truncate(n);
+        consumers
+    }
+
+    /// Returns the id which Tokio assigned to the running task with the given id, for correlating
+    /// this crate's diagnostics with Tokio's own tooling such as `tokio-console`. Returns [`None`]
+    /// if no such task is running, or if it has only just been spawned.
+    pub fn tokio_task_id(&self, id: TaskId) -> Option<tokio::task::Id> {
+        self.0.shared.registry.tasks().get(&id)?.tokio_id()
+    }
+
+    /// The reverse of [`tokio_task_id`](Self::tokio_task_id), returning the id of the running task
+    /// to which Tokio assigned `tokio_id`. Tokio ids are only unique among running tasks, so this
+    /// only finds tasks which have not finished yet.
+    pub fn task_id_for_tokio_task(&self, tokio_id: tokio::task::Id) -> Option<TaskId> {
+        self.0
+            .shared
+            .registry
+            .tasks()
+            .iter()
+            .find(|(_, entry)| entry.tokio_id() == Some(tokio_id))
+            .map(|(&id, _)| id)
+    }
+
+    /// Returns the most recent tasks to have panicked, oldest first. Only the last few panics are
+    /// remembered.
+    pub fn recent_panics(&self) -> Vec<PanickedTask> {
+        self.0
+            .shared
+            .registry
+            .recent_panics()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the number of background tasks which have been spawned and have not finished yet.
+    /// Tasks which have only been [reserved](Self::reserve_task) are not counted.
+    pub fn live_task_count(&self) -> usize {
+        self.0.shared.registry.live_task_count()
+    }
+
+    /// Returns whether the runtime is draining, which it starts doing as soon as an
+    /// [`AppExit`](bevy_app::AppExit) event is seen. While draining, no new tasks are admitted,
+    /// so that tasks which keep spawning more work cannot stop the set of live tasks from winding
+    /// down. Every spawn method, including those on [`TaskContext`] and
+    /// [`TaskSpawner`], still returns a [`JoinHandle`], but the task is never run and its handle
+    /// resolves with a [cancellation](tokio::task::JoinError::is_cancelled) error. Tasks which
+    /// were already running are not affected.
+    pub fn is_draining(&self) -> bool {
+        self.0.shared.registry.is_draining()
+    }
+
+    /// Allocates a [`TaskId`] and registers it without starting any work. This allows callers to
+    /// set up any state keyed on the id before the task runs, avoiding a race where the task
+    /// finishes before its id is known. Use [`TaskSlot::start`] to spawn the task.
+    #[track_caller]
+    pub fn reserve_task(&self) -> TaskSlot {
+        self.reserve_task_at(Location::caller())
+    }
+
+    /// Like [`reserve_task`](Self::reserve_task), but records the given spawn location rather than
+    /// the caller's.
+    pub(crate) fn reserve_task_at(&self, spawned_at: &'static Location<'static>) -> TaskSlot {
+        let registration = self.0.shared.registry.register(spawned_at);
+        let context = self.new_task_context(registration.id());
+        TaskSlot {
+            registration,
+            context,
+            categories: self.0.categories.clone(),
+            runtime: self.0.runtime.handle().clone(),
+            spawned_at,
+            executor: self.0.shared.registry.executor,
+            #[cfg(feature = "task-logs")]
+            log_capture: crate::LogCapture::for_plugin(self.0.capture_named_task_logs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use bevy_app::{AppExit, PostUpdate};
+    use bevy_ecs::change_detection::DetectChanges;
+    use bevy_ecs::system::{Res, ResMut, Resource};
+
+    use std::time::Duration;
+
+    use bevy_app::App;
+    use tokio::task::JoinHandle;
+
+    use crate::{testing, LiveTaskCount, TaskId, TokioTasksRuntime};
+
+    #[test]
+    fn batch_spawned_tasks_record_their_abort_handles() {
+        let app = testing::app();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let handles =
+            runtime.spawn_background_tasks((0..3).map(|_| |_ctx| std::future::pending::<()>()));
+        let tasks = runtime.tasks();
+        assert_eq!(tasks.len(), 3);
+        for (task, handle) in tasks.iter().zip(&handles) {
+            assert_eq!(task.tokio_id, Some(handle.id()));
+            assert!(runtime.abort_task(task.id));
+        }
+        testing::poll_tasks(&app);
+        assert!(handles.iter().all(|handle| handle.is_finished()));
+        assert!(runtime.tasks().is_empty());
+    }
+
+    #[test]
+    fn tasks_spawned_while_draining_are_cancelled_without_running() {
+        let mut app = testing::app();
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+
+        let ran = Arc::new(AtomicBool::new(false));
+        let spawnable_ran = ran.clone();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert!(runtime.is_draining());
+        let handle = runtime.spawn_background_task(move |_ctx| {
+            spawnable_ran.store(true, Ordering::SeqCst);
+            async move {}
+        });
+        let error = runtime
+            .runtime()
+            .block_on(handle)
+            .expect_err("Task spawned while draining was run");
+        assert!(error.is_cancelled());
+        assert!(!ran.load(Ordering::SeqCst));
+        assert!(runtime.tasks().is_empty());
+    }
+
+    #[test]
+    fn dropping_an_unstarted_slot_removes_its_task() {
+        let app = testing::app();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let slot = runtime.reserve_task();
+        let tasks = runtime.tasks();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, slot.id());
+        assert_eq!(tasks[0].age, None);
+        drop(slot);
+        assert!(runtime.tasks().is_empty());
+        assert_eq!(runtime.live_task_count(), 0);
+    }
+
+    /// Whether [`LiveTaskCount`] was changed on each update, and its value.
+    #[derive(Resource, Default)]
+    struct CountChanges(Vec<(bool, usize)>);
+
+    fn record_count_changes(count: Res<LiveTaskCount>, mut changes: ResMut<CountChanges>) {
+        changes.0.push((count.is_changed(), count.0));
+    }
+
+    #[test]
+    fn live_task_count_only_changes_when_the_count_moves() {
+        let mut app = testing::app();
+        app.init_resource::<CountChanges>();
+        app.add_systems(PostUpdate, record_count_changes);
+        app.update();
+        app.update();
+        app.update();
+        let handle = testing::spawn(&app, |_ctx| std::future::pending::<()>());
+        app.update();
+        app.update();
+        handle.abort();
+        app.update();
+        app.update();
+        assert_eq!(
+            app.world().resource::<CountChanges>().0,
+            vec![
+                // The first run of a system sees the resource as changed, since it was added.
+                (true, 0),
+                (false, 0),
+                (false, 0),
+                (true, 1),
+                (false, 1),
+                (true, 0),
+                (false, 0),
+            ]
+        );
+    }
+
+    fn task_id<Output>(app: &App, handle: &JoinHandle<Output>) -> TaskId {
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        runtime
+            .task_id_for_tokio_task(handle.id())
+            .expect("Task is not registered")
+    }
+
+    /// Spawns a task which runs one callback taking `cost`, and then keeps running so that its
+    /// cost stays attributed to it.
+    fn spawn_costly_task(app: &App, cost: Duration) -> JoinHandle<()> {
+        testing::spawn(app, move |mut ctx| async move {
+            ctx.run_on_main_thread(move |_| std::thread::sleep(cost))
+                .await;
+            std::future::pending::<()>().await;
+        })
+    }
+
+    #[test]
+    fn the_most_expensive_task_is_the_top_main_thread_consumer() {
+        let mut app = testing::app();
+        let cheap = spawn_costly_task(&app, Duration::ZERO);
+        let costly = spawn_costly_task(&app, Duration::from_millis(5));
+        let idle = testing::spawn(&app, |_ctx| std::future::pending::<()>());
+        let (cheap, costly, idle) = (
+            task_id(&app, &cheap),
+            task_id(&app, &costly),
+            task_id(&app, &idle),
+        );
+        for _ in 0..2 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let cost = runtime
+            .task_main_thread_cost(costly)
+            .expect("Task has finished");
+        assert!(cost >= Duration::from_millis(5));
+        assert_eq!(runtime.task_main_thread_cost(idle), Some(Duration::ZERO));
+        let top: Vec<_> = runtime
+            .top_main_thread_consumers(3)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        // The cheap callback may be too quick to register any cost at all.
+        assert!(top == vec![costly, cheap] || top == vec![costly]);
+    }
+}
@@ -0,0 +1,214 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+
+use crate::{RequestChannelInfo, TaskContext, TokioTasksRuntime};
+
+type RequestPair<Req, Resp> = (Req, oneshot::Sender<Resp>);
+type SharedRequestReceiver<Req, Resp> =
+    Arc<tokio::sync::Mutex<UnboundedReceiver<RequestPair<Req, Resp>>>>;
+type ErasedRequestChannel = Box<dyn AnyRequestChannel>;
+
+/// A type-keyed collection of the request channels created by
+/// [`request`](TokioTasksRuntime::request) and
+/// [`subscribe_requests`](TaskContext::subscribe_requests).
+#[derive(Clone, Default)]
+pub(crate) struct RequestChannels(Arc<Mutex<HashMap<(TypeId, TypeId), ErasedRequestChannel>>>);
+
+struct RequestChannel<Req, Resp> {
+    request_tx: UnboundedSender<RequestPair<Req, Resp>>,
+    request_rx: SharedRequestReceiver<Req, Resp>,
+    /// The number of requests which have been sent but not received yet. The receiver is locked
+    /// while a subscriber waits on it, so its own length cannot be read reliably.
+    queued_requests: Arc<AtomicUsize>,
+}
+
+impl<Req, Resp> Clone for RequestChannel<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self {
+            request_tx: self.request_tx.clone(),
+            request_rx: self.request_rx.clone(),
+            queued_requests: self.queued_requests.clone(),
+        }
+    }
+}
+
+/// A [`RequestChannel`] with its types erased, so that channels of every type can be stored
+/// together.
+trait AnyRequestChannel: Send {
+    fn as_any(&self) -> &dyn Any;
+    fn info(&self) -> RequestChannelInfo;
+}
+
+impl<Req, Resp> AnyRequestChannel for RequestChannel<Req, Resp>
+where
+    Req: Send + 'static,
+    Resp: Send + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn info(&self) -> RequestChannelInfo {
+        RequestChannelInfo {
+            request_type: std::any::type_name::<Req>(),
+            response_type: std::any::type_name::<Resp>(),
+            queued_requests: self.queued_requests.load(Ordering::SeqCst),
+            // Every receiver shares the channel's copy of the receiver.
+            subscribers: Arc::strong_count(&self.request_rx) - 1,
+        }
+    }
+}
+
+impl RequestChannels {
+    /// Returns the channel for the given request and response types, creating it if neither side
+    /// has used it yet.
+    fn channel<Req, Resp>(&self) -> RequestChannel<Req, Resp>
+    where
+        Req: Send + 'static,
+        Resp: Send + 'static,
+    {
+        let mut channels = self.lock();
+        channels
+            .entry((TypeId::of::<Req>(), TypeId::of::<Resp>()))
+            .or_insert_with(|| {
+                let (request_tx, request_rx) = tokio::sync::mpsc::unbounded_channel();
+                Box::new(RequestChannel::<Req, Resp> {
+                    request_tx,
+                    request_rx: Arc::new(tokio::sync::Mutex::new(request_rx)),
+                    queued_requests: Arc::default(),
+                })
+            })
+            .as_any()
+            .downcast_ref::<RequestChannel<Req, Resp>>()
+            .expect("Request channel was registered with mismatched types")
+            .clone()
+    }
+
+    pub(crate) fn infos(&self) -> Vec<RequestChannelInfo> {
+        self.lock().values().map(|channel| channel.info()).collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(TypeId, TypeId), ErasedRequestChannel>> {
+        self.0.lock().expect("Request channels mutex was poisoned")
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Sends a request to the background tasks which have subscribed to requests of type `Req`
+    /// using [`subscribe_requests`](TaskContext::subscribe_requests). The returned receiver
+    /// resolves with the task's response, and can either be awaited or polled from a system each
+    /// frame using [`try_recv`](oneshot::Receiver::try_recv). If the responding task drops the
+    /// responder without replying, the receiver reports an error instead.
+    ///
+    /// Requests sent before any task has subscribed are queued until a task starts receiving.
+    pub fn request<Req, Resp>(&self, request: Req) -> oneshot::Receiver<Resp>
+    where
+        Req: Send + 'static,
+        Resp: Send + 'static,
+    {
+        let channel = self.0.shared.request_channels.channel::<Req, Resp>();
+        let (response_tx, response_rx) = oneshot::channel();
+        // Counted before sending, so that a receiver can never uncount it first.
+        channel.queued_requests.fetch_add(1, Ordering::SeqCst);
+        if channel.request_tx.send((request, response_tx)).is_err() {
+            panic!("Failed to send request to background tasks");
+        }
+        response_rx
+    }
+}
+
+impl TaskContext {
+    /// Subscribes to the requests of type `Req` which systems send using
+    /// [`request`](TokioTasksRuntime::request). If several tasks subscribe to the same request
+    /// type, each request is delivered to only one of them.
+    pub fn subscribe_requests<Req, Resp>(&self) -> RequestReceiver<Req, Resp>
+    where
+        Req: Send + 'static,
+        Resp: Send + 'static,
+    {
+        let channel = self.shared.request_channels.channel::<Req, Resp>();
+        RequestReceiver {
+            request_rx: channel.request_rx,
+            queued_requests: channel.queued_requests,
+        }
+    }
+}
+
+/// Receives requests sent from the main thread using [`request`](TokioTasksRuntime::request).
+/// Created by [`subscribe_requests`](TaskContext::subscribe_requests).
+pub struct RequestReceiver<Req, Resp> {
+    request_rx: SharedRequestReceiver<Req, Resp>,
+    queued_requests: Arc<AtomicUsize>,
+}
+
+impl<Req, Resp> RequestReceiver<Req, Resp> {
+    /// Waits for the next request, returning it along with the responder which should be used to
+    /// send the response back to the requester.
+    pub async fn recv(&mut self) -> (Req, oneshot::Sender<Resp>) {
+        let request = self
+            .request_rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .expect("Request channel was closed");
+        self.queued_requests.fetch_sub(1, Ordering::SeqCst);
+        request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing, RequestChannelInfo, TokioTasksRuntime};
+
+    struct Double(u32);
+
+    fn channel_info(runtime: &TokioTasksRuntime) -> RequestChannelInfo {
+        let mut channels = runtime.debug_registries().request_channels;
+        assert_eq!(channels.len(), 1);
+        channels.remove(0)
+    }
+
+    #[test]
+    fn requests_queued_before_subscribing_are_answered() {
+        let mut app = testing::app();
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let mut responses = vec![
+            runtime.request::<Double, u32>(Double(1)),
+            runtime.request::<Double, u32>(Double(2)),
+        ];
+        let info = channel_info(runtime);
+        assert_eq!(info.queued_requests, 2);
+        assert_eq!(info.subscribers, 0);
+
+        let handle = testing::spawn(&app, |ctx| async move {
+            let mut requests = ctx.subscribe_requests::<Double, u32>();
+            for _ in 0..3 {
+                let (Double(value), responder) = requests.recv().await;
+                let _ = responder.send(value * 2);
+            }
+        });
+        testing::poll_tasks(&app);
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let info = channel_info(runtime);
+        assert_eq!(info.queued_requests, 0);
+        assert_eq!(info.subscribers, 1);
+        responses.push(runtime.request::<Double, u32>(Double(3)));
+        testing::run_until_finished(&mut app, handle);
+
+        let answers: Vec<_> = responses
+            .iter_mut()
+            .map(|response| response.try_recv().expect("Request was not answered"))
+            .collect();
+        assert_eq!(answers, vec![2, 4, 6]);
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        let info = channel_info(runtime);
+        assert_eq!(info.queued_requests, 0);
+        assert_eq!(info.subscribers, 0);
+    }
+}
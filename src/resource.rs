@@ -0,0 +1,93 @@
+use std::panic::Location;
+
+use bevy_ecs::system::Resource;
+
+use crate::{MainThreadJoin, TaskContext};
+
+impl TaskContext {
+    /// Waits until the resource `R` is not present in the main Bevy
+    /// [`World`](bevy_ecs::world::World), such as a `LoadingScreen` marker resource being taken
+    /// down. Presence is checked on the main thread once per update tick, starting with the tick
+    /// after this is called, so this resolves on that tick if the resource is already absent.
+    ///
+    /// Waits forever if the resource is never removed. Use
+    /// [`wait_for_resource_removed_timeout`](Self::wait_for_resource_removed_timeout) to give up
+    /// after a number of ticks.
+    #[track_caller]
+    pub fn wait_for_resource_removed<R: Resource>(&mut self) -> MainThreadJoin<()> {
+        self.run_each_tick_until(
+            |ctx| (!ctx.world.contains_resource::<R>()).then_some(()),
+            Location::caller(),
+        )
+    }
+
+    /// Like [`wait_for_resource_removed`](Self::wait_for_resource_removed), but gives up once `R`
+    /// has been checked for on `max_updates` update ticks and was still present. Resolves with
+    /// `true` if the resource was removed, and `false` if it timed out.
+    #[track_caller]
+    pub fn wait_for_resource_removed_timeout<R: Resource>(
+        &mut self,
+        max_updates: usize,
+    ) -> MainThreadJoin<bool> {
+        let mut checks = 0;
+        self.run_each_tick_until(
+            move |ctx| {
+                if !ctx.world.contains_resource::<R>() {
+                    return Some(true);
+                }
+                checks += 1;
+                (checks >= max_updates).then_some(false)
+            },
+            Location::caller(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::Resource;
+
+    use crate::testing;
+
+    #[derive(Resource)]
+    struct LoadingScreen;
+
+    #[test]
+    fn wait_for_resource_removed_resolves_when_already_absent() {
+        let mut app = testing::app();
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            ctx.wait_for_resource_removed::<LoadingScreen>().await;
+            ctx.wait_for_resource_removed_timeout::<LoadingScreen>(3)
+                .await
+        });
+        assert!(testing::run_until_finished(&mut app, handle));
+    }
+
+    #[test]
+    fn wait_for_resource_removed_resolves_once_the_resource_is_removed() {
+        let mut app = testing::app();
+        app.insert_resource(LoadingScreen);
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            ctx.wait_for_resource_removed::<LoadingScreen>().await
+        });
+        for _ in 0..3 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        assert!(!handle.is_finished());
+        app.world_mut().remove_resource::<LoadingScreen>();
+        testing::run_until_finished(&mut app, handle);
+    }
+
+    #[test]
+    fn wait_for_resource_removed_timeout_gives_up_while_the_resource_is_present() {
+        let mut app = testing::app();
+        app.insert_resource(LoadingScreen);
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            ctx.wait_for_resource_removed_timeout::<LoadingScreen>(3)
+                .await
+        });
+        assert!(!testing::run_until_finished(&mut app, handle));
+        assert!(app.world().contains_resource::<LoadingScreen>());
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::Location;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bevy_ecs::world::World;
+
+use crate::{
+    join_callback, IfAbandoned, MainThreadCallback, MainThreadContext, MainThreadFailureKind,
+    MainThreadJoin, TaskContext, TokioTasksRuntime,
+};
+
+/// Creates the two halves of the queue of callbacks scheduled to run at specific ticks.
+pub(crate) fn scheduled_channel() -> (ScheduledCallbackSender, ScheduledCallbacks) {
+    let (callback_tx, callback_rx) = tokio::sync::mpsc::unbounded_channel();
+    let unreceived = Arc::new(AtomicUsize::new(0));
+    (
+        ScheduledCallbackSender {
+            callback_tx,
+            unreceived: unreceived.clone(),
+        },
+        ScheduledCallbacks {
+            callback_rx,
+            unreceived,
+            by_tick: Mutex::new(BTreeMap::new()),
+            next_due_tick: AtomicUsize::new(usize::MAX),
+        },
+    )
+}
+
+/// Sends callbacks to be held on the main thread until the tick they are scheduled for.
+#[derive(Clone)]
+pub(crate) struct ScheduledCallbackSender {
+    callback_tx: tokio::sync::mpsc::UnboundedSender<(usize, MainThreadCallback)>,
+    /// The number of callbacks which have been sent but not yet received by the main thread.
+    unreceived: Arc<AtomicUsize>,
+}
+
+impl ScheduledCallbackSender {
+    fn send(
+        &self,
+        target_tick: usize,
+        callback: MainThreadCallback,
+    ) -> Result<(), MainThreadFailureKind> {
+        // Count the callback before sending it so the main thread can never observe a callback
+        // which has not been counted yet.
+        self.unreceived.fetch_add(1, Ordering::SeqCst);
+        self.callback_tx.send((target_tick, callback)).map_err(|_| {
+            self.unreceived.fetch_sub(1, Ordering::SeqCst);
+            MainThreadFailureKind::Enqueue
+        })
+    }
+}
+
+/// The callbacks scheduled to run at specific ticks, held by the main thread in a map keyed by
+/// tick so that callbacks scheduled far in the future cost nothing on the ticks before then.
+pub(crate) struct ScheduledCallbacks {
+    callback_rx: tokio::sync::mpsc::UnboundedReceiver<(usize, MainThreadCallback)>,
+    unreceived: Arc<AtomicUsize>,
+    /// Wrapped in a [`Mutex`] only so that the runtime resource is [`Sync`]. It is always
+    /// accessed through [`Mutex::get_mut`], so it is never actually locked.
+    by_tick: Mutex<BTreeMap<usize, Vec<MainThreadCallback>>>,
+    /// The earliest tick in `by_tick`, or [`usize::MAX`] if it is empty. This lets idle ticks
+    /// check for due callbacks without touching the map.
+    next_due_tick: AtomicUsize,
+}
+
+impl ScheduledCallbacks {
+    /// Returns whether any callbacks may be due on the given tick.
+    pub(crate) fn has_due(&self, current_tick: usize) -> bool {
+        self.unreceived.load(Ordering::SeqCst) > 0
+            || self.next_due_tick.load(Ordering::SeqCst) <= current_tick
+    }
+
+    /// Removes and returns every callback scheduled for the given tick or earlier, in the order
+    /// of the ticks they were scheduled for.
+    pub(crate) fn take_due(&mut self, current_tick: usize) -> Vec<MainThreadCallback> {
+        let by_tick = self
+            .by_tick
+            .get_mut()
+            .expect("Scheduled callbacks mutex was poisoned");
+        while let Ok((target_tick, callback)) = self.callback_rx.try_recv() {
+            self.unreceived.fetch_sub(1, Ordering::SeqCst);
+            by_tick.entry(target_tick).or_default().push(callback);
+        }
+        let later = match current_tick.checked_add(1) {
+            Some(next_tick) => by_tick.split_off(&next_tick),
+            None => BTreeMap::new(),
+        };
+        let due = std::mem::replace(by_tick, later);
+        self.next_due_tick.store(
+            by_tick.keys().next().copied().unwrap_or(usize::MAX),
+            Ordering::SeqCst,
+        );
+        due.into_values().flatten().collect()
+    }
+}
+
+impl TaskContext {
+    /// Runs a callback on the main thread during the update tick numbered `target_tick`, as
+    /// reported by [`current_tick`](Self::current_tick), and resolves with its output. Unlike
+    /// sleeping with [`sleep_updates`](Self::sleep_updates) and then calling
+    /// [`run_on_main_thread`](Self::run_on_main_thread), the callback is guaranteed to run on
+    /// exactly that tick rather than whichever tick the task gets to after waking up, and the
+    /// task does not need to stay awake in the meantime. If `target_tick` has already passed,
+    /// the callback runs on the next tick.
+    ///
+    /// Scheduled callbacks run after that tick's queued callbacks and are not limited by the
+    /// [`DrainBudget`](crate::DrainBudget). Callbacks scheduled for the same tick run in the order
+    /// they were scheduled in. Like with [`run_on_main_thread`](Self::run_on_main_thread), the
+    /// callback is skipped if the returned future is dropped before the tick arrives.
+    #[track_caller]
+    pub fn run_on_main_thread_at_tick<Runnable, Output>(
+        &mut self,
+        target_tick: usize,
+        runnable: Runnable,
+    ) -> impl Future<Output = Output> + Send + '_
+    where
+        Runnable: FnOnce(MainThreadContext) -> Output + Send + 'static,
+        Output: Send + 'static,
+    {
+        let origin = self.callback_origin(Location::caller());
+        async move {
+            let (callback, join) = join_callback(origin, IfAbandoned::Skip, runnable);
+            let callback = self.shared.main_thread_tx.wrap(callback);
+            if let Err(kind) = self
+                .shared
+                .main_thread_tx
+                .scheduled_tx
+                .send(target_tick, callback)
+            {
+                join.origin.fail(kind);
+            }
+            join.await
+        }
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Queues a callback which runs once with mutable access to the [`World`] during the update
+    /// tick numbered `target_tick`, as reported by
+    /// [`current_tick`](TaskContext::current_tick). This is the system side counterpart of
+    /// [`run_on_main_thread_at_tick`](TaskContext::run_on_main_thread_at_tick), for timeline
+    /// driven events which are configured from ordinary systems. If `target_tick` has already
+    /// passed, the callback runs on the next tick. A system can be run from the callback using
+    /// [`World::run_system_once`](bevy_ecs::system::RunSystemOnce::run_system_once).
+    ///
+    /// The callback runs at the same point in the tick as scheduled task callbacks, and the
+    /// returned [`MainThreadJoin`] can be awaited from a task to wait for it, or simply dropped.
+    #[track_caller]
+    pub fn on_tick_reached<Runnable>(
+        &self,
+        target_tick: usize,
+        runnable: Runnable,
+    ) -> MainThreadJoin<()>
+    where
+        Runnable: FnOnce(&mut World) + Send + 'static,
+    {
+        let origin = self.main_thread_origin(Location::caller());
+        let (callback, join) =
+            join_callback(origin, IfAbandoned::Run, move |ctx| runnable(ctx.world));
+        let main_thread_tx = &self.0.shared.main_thread_tx;
+        let callback = main_thread_tx.wrap(callback);
+        if let Err(kind) = main_thread_tx.scheduled_tx.send(target_tick, callback) {
+            join.origin.fail(kind);
+        }
+        join
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::Resource;
+
+    use crate::{testing, DrainBudget, TokioTasksRuntime};
+
+    #[derive(Resource)]
+    struct Reached;
+
+    #[test]
+    fn scheduled_callbacks_run_on_their_tick_regardless_of_the_budget() {
+        let mut app = testing::app();
+        // A budget which would hold back every queued callback.
+        app.insert_resource(DrainBudget {
+            max_callbacks: Some(0),
+            max_duration: None,
+        });
+        let handle = testing::spawn(&app, |mut ctx| async move {
+            let target_tick = ctx.current_tick() + 3;
+            let ran_on = ctx
+                .run_on_main_thread_at_tick(target_tick, |ctx| ctx.current_tick)
+                .await;
+            let scheduled_at = ctx.current_tick();
+            let past_ran_on = ctx
+                .run_on_main_thread_at_tick(0, |ctx| ctx.current_tick)
+                .await;
+            (target_tick, ran_on, scheduled_at, past_ran_on)
+        });
+        let (target_tick, ran_on, scheduled_at, past_ran_on) =
+            testing::run_until_finished(&mut app, handle);
+        assert_eq!(ran_on, target_tick);
+        assert_eq!(past_ran_on, scheduled_at + 1);
+    }
+
+    #[test]
+    fn on_tick_reached_runs_on_its_target_tick() {
+        let mut app = testing::app();
+        let target_tick = testing::context(&mut app).current_tick() + 2;
+        drop(
+            app.world()
+                .resource::<TokioTasksRuntime>()
+                .on_tick_reached(target_tick, |world| world.insert_resource(Reached)),
+        );
+        app.update();
+        assert!(!app.world().contains_resource::<Reached>());
+        app.update();
+        assert!(app.world().contains_resource::<Reached>());
+    }
+}
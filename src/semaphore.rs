@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{SemaphoreInfo, TaskContext};
+
+/// The named semaphores configured through [`semaphores`](crate::TokioTasksPlugin::semaphores).
+#[derive(Clone, Default)]
+pub(crate) struct SemaphoreRegistry(Arc<HashMap<String, Arc<Semaphore>>>);
+
+impl SemaphoreRegistry {
+    pub(crate) fn new(permits_by_name: &HashMap<String, usize>) -> Self {
+        Self(Arc::new(
+            permits_by_name
+                .iter()
+                .map(|(name, permits)| (name.clone(), Arc::new(Semaphore::new(*permits))))
+                .collect(),
+        ))
+    }
+
+    pub(crate) fn infos(&self) -> Vec<SemaphoreInfo> {
+        self.0
+            .iter()
+            .map(|(name, semaphore)| SemaphoreInfo {
+                name: name.clone(),
+                available_permits: semaphore.available_permits(),
+            })
+            .collect()
+    }
+}
+
+/// A permit acquired from one of the plugin's named semaphores using
+/// [`acquire_permit`](TaskContext::acquire_permit). The permit is returned to the semaphore when
+/// this is dropped.
+#[derive(Debug)]
+pub struct Permit {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl TaskContext {
+    /// Waits until a permit is available from the named semaphore, allowing concurrency limits on
+    /// shared resources to be coordinated across independently spawned tasks. Semaphores are
+    /// configured using [`semaphores`](crate::TokioTasksPlugin::semaphores).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no semaphore with the given name was configured on the plugin.
+    pub async fn acquire_permit(&self, name: &str) -> Permit {
+        let semaphore = match self.shared.semaphores.0.get(name) {
+            Some(semaphore) => semaphore.clone(),
+            None => panic!("No semaphore named \"{name}\" was registered on the TokioTasksPlugin"),
+        };
+        let permit = semaphore
+            .acquire_owned()
+            .await
+            .expect("Named semaphores are never closed");
+        Permit { _permit: permit }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{testing, TokioTasksPlugin};
+
+    #[test]
+    fn permits_are_held_until_dropped() {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            semaphores: HashMap::from([("database".to_owned(), 1)]),
+            ..testing::plugin()
+        });
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let holder = testing::spawn(&app, |ctx| async move {
+            let permit = ctx.acquire_permit("database").await;
+            let _ = release_rx.await;
+            drop(permit);
+        });
+        testing::poll_tasks(&app);
+        let waiter = testing::spawn(&app, |ctx| async move {
+            let _permit = ctx.acquire_permit("database").await;
+        });
+        for _ in 0..3 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        assert!(!waiter.is_finished());
+
+        release_tx.send(()).expect("The holder stopped waiting");
+        testing::run_until_finished(&mut app, holder);
+        testing::run_until_finished(&mut app, waiter);
+    }
+}
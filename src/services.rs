@@ -0,0 +1,84 @@
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::TaskContext;
+
+/// A type-keyed container of shared service objects, such as database pools and HTTP clients,
+/// which every background task can look up using [`service`](TaskContext::service) instead of
+/// having them threaded through every function. At most one service of each type is stored.
+/// Services are configured through [`services`](crate::TokioTasksPlugin::services) and cannot be
+/// changed once the plugin is built.
+///
+/// ```
+/// # use bevy_tokio_tasks::{TaskServices, TokioTasksPlugin};
+/// struct HttpClient;
+/// struct DatabasePool;
+///
+/// let plugin = TokioTasksPlugin {
+///     services: TaskServices::new().with(HttpClient).with(DatabasePool),
+///     ..TokioTasksPlugin::default()
+/// };
+/// ```
+#[derive(Clone, Default)]
+pub struct TaskServices(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl TaskServices {
+    /// Creates an empty service container.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `service`, replacing any service of the same type which was already registered.
+    pub fn insert<S: Send + Sync + 'static>(&mut self, service: S) {
+        self.insert_arc(Arc::new(service));
+    }
+
+    /// Like [`insert`](Self::insert), but for a service which is already shared elsewhere in the
+    /// app.
+    pub fn insert_arc<S: Send + Sync + 'static>(&mut self, service: Arc<S>) {
+        self.0.insert(TypeId::of::<S>(), service);
+    }
+
+    /// Registers `service` in the same way as [`insert`](Self::insert), returning the container
+    /// for chaining.
+    pub fn with<S: Send + Sync + 'static>(mut self, service: S) -> Self {
+        self.insert(service);
+        self
+    }
+
+    /// Returns the registered service of type `S`, if there is one.
+    pub fn get<S: Send + Sync + 'static>(&self) -> Option<Arc<S>> {
+        let service = self.0.get(&TypeId::of::<S>())?.clone();
+        Some(
+            service
+                .downcast()
+                .expect("Services are keyed by their own type id"),
+        )
+    }
+}
+
+impl TaskContext {
+    /// Returns the shared service of type `S` which was registered through
+    /// [`services`](crate::TokioTasksPlugin::services).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no service of type `S` was registered on the plugin. Use
+    /// [`try_service`](Self::try_service) for services which are optional.
+    pub fn service<S: Send + Sync + 'static>(&self) -> Arc<S> {
+        match self.try_service() {
+            Some(service) => service,
+            None => panic!(
+                "No service of type {} was registered on the TokioTasksPlugin",
+                type_name::<S>()
+            ),
+        }
+    }
+
+    /// Like [`service`](Self::service), but returns [`None`] if no service of type `S` was
+    /// registered.
+    pub fn try_service<S: Send + Sync + 'static>(&self) -> Option<Arc<S>> {
+        self.shared.services.get()
+    }
+}
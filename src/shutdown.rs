@@ -0,0 +1,311 @@
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::thread::ThreadId;
+
+use bevy_app::AppExit;
+use bevy_ecs::event::EventReader;
+use bevy_ecs::system::SystemState;
+use bevy_ecs::world::World;
+
+use crate::{
+    join_callback, IfAbandoned, MainThreadCallback, MainThreadContext, MainThreadJoin, TaskContext,
+    TokioTasksRuntime,
+};
+
+/// A callback which runs at most once, from whichever of the normal queue and the shutdown list
+/// gets to it first.
+type SharedCallback = Arc<Mutex<Option<MainThreadCallback>>>;
+
+/// The callbacks queued using
+/// [`enqueue_main_thread_must_run`](TaskContext::enqueue_main_thread_must_run) which have not run
+/// yet.
+#[derive(Clone, Default)]
+pub(crate) struct ShutdownCallbacks(Arc<Mutex<Vec<SharedCallback>>>);
+
+impl ShutdownCallbacks {
+    /// Remembers `callback` so that it can be run during shutdown, and returns a callback to queue
+    /// as normal which runs it unless it already ran during shutdown.
+    fn register(&self, callback: MainThreadCallback) -> MainThreadCallback {
+        let shared: SharedCallback = Arc::new(Mutex::new(Some(callback)));
+        let mut pending = self.pending();
+        // Forget callbacks which have already run normally, so the list only grows while the
+        // main thread is not keeping up.
+        pending.retain(|shared| shared.lock().is_ok_and(|callback| callback.is_some()));
+        pending.push(shared.clone());
+        drop(pending);
+        Box::new(move |ctx| {
+            let callback = shared
+                .lock()
+                .expect("Shutdown callback mutex was poisoned")
+                .take();
+            if let Some(callback) = callback {
+                callback(ctx);
+            }
+        })
+    }
+
+    /// Takes every callback which has not run yet.
+    fn take_all(&self) -> Vec<MainThreadCallback> {
+        self.pending()
+            .drain(..)
+            .filter_map(|shared| shared.lock().ok()?.take())
+            .collect()
+    }
+
+    fn pending(&self) -> MutexGuard<'_, Vec<SharedCallback>> {
+        self.0
+            .lock()
+            .expect("Shutdown callbacks mutex was poisoned")
+    }
+}
+
+/// A cleanup closure registered using [`on_shutdown`](TokioTasksRuntime::on_shutdown).
+type ShutdownHook = Box<dyn FnOnce() + Send + 'static>;
+
+/// The closures registered using [`on_shutdown`](TokioTasksRuntime::on_shutdown) which have not
+/// run yet.
+#[derive(Clone)]
+pub(crate) struct ShutdownHooks(Arc<ShutdownHooksInner>);
+
+struct ShutdownHooksInner {
+    hooks: Mutex<Vec<ShutdownHook>>,
+    /// The thread which the plugin was built on, whose panics are the ones which bring the app
+    /// down.
+    main_thread: ThreadId,
+    panic_hook_installed: AtomicBool,
+}
+
+impl ShutdownHooks {
+    /// Creates the hooks for a runtime which is being created on the main thread.
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(ShutdownHooksInner {
+            hooks: Mutex::default(),
+            main_thread: std::thread::current().id(),
+            panic_hook_installed: AtomicBool::new(false),
+        }))
+    }
+
+    fn register(&self, hook: ShutdownHook) {
+        self.0
+            .hooks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(hook);
+        if !self.0.panic_hook_installed.swap(true, Ordering::SeqCst) {
+            install_panic_hook(Arc::downgrade(&self.0));
+        }
+    }
+
+    /// Runs every hook which has not run yet, in the order they were registered.
+    fn run_all(&self) {
+        let hooks =
+            std::mem::take(&mut *self.0.hooks.lock().unwrap_or_else(PoisonError::into_inner));
+        for hook in hooks {
+            hook();
+        }
+    }
+}
+
+/// Chains a panic hook which runs the shutdown hooks when the main thread panics, before calling
+/// the previously installed hook.
+fn install_panic_hook(hooks: Weak<ShutdownHooksInner>) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(hooks) = hooks.upgrade() {
+            // Panics on other threads, inside tasks polled on the main thread, or in callbacks
+            // whose panics are caught do not bring the app down. The lock is only tried, since
+            // the panic may have happened while it was held.
+            let app_is_panicking = std::thread::current().id() == hooks.main_thread
+                && tokio::runtime::Handle::try_current().is_err()
+                && !crate::panic::catching_callback_panics();
+            if app_is_panicking {
+                if let Ok(mut pending) = hooks.hooks.try_lock() {
+                    let pending = std::mem::take(&mut *pending);
+                    for hook in pending {
+                        hook();
+                    }
+                }
+            }
+        }
+        previous(info);
+    }));
+}
+
+impl TokioTasksRuntime {
+    /// Registers a synchronous cleanup closure which runs once on the way out of the app, for
+    /// "always do this before exiting" logic such as flushing a log file or releasing a lock
+    /// file. It runs during the [`Last`](bevy_app::Last) schedule of the frame on which an
+    /// [`AppExit`] event is sent, after any
+    /// [`enqueue_main_thread_must_run`](TaskContext::enqueue_main_thread_must_run) callbacks, or
+    /// from a panic hook if the main thread panics first. Closures run in the order they were
+    /// registered.
+    ///
+    /// Registering the first closure installs a panic hook which runs the closures before calling
+    /// the previously installed hook. Panics in background tasks, and main thread callbacks whose
+    /// panics are caught because of [`catch_panics`](crate::TokioTasksPlugin::catch_panics), do
+    /// not count, since they do not bring the app down. Nothing runs if the app is killed or
+    /// aborts without unwinding, or exits without sending [`AppExit`].
+    ///
+    /// Since they can run from inside a panic hook, the closures must be fast, must not panic,
+    /// and have no access to the world. Cleanup which needs the world should use
+    /// [`enqueue_main_thread_must_run`](TaskContext::enqueue_main_thread_must_run) instead.
+    pub fn on_shutdown(&self, hook: impl FnOnce() + Send + 'static) {
+        self.0.shutdown_hooks.register(Box::new(hook));
+    }
+}
+
+impl TaskContext {
+    /// Queues a callback on the main thread in the same way as
+    /// [`enqueue_main_thread`](Self::enqueue_main_thread), but which is guaranteed to run even if
+    /// the app exits before the next tick gets to it. This is meant for essential final effects,
+    /// such as flushing a task's results to disk. When an [`AppExit`] event is sent, every such
+    /// callback which has not run yet is run during the [`Last`](bevy_app::Last) schedule of that
+    /// frame, ignoring the [`DrainBudget`](crate::DrainBudget) and
+    /// [`DrainControl`](crate::DrainControl). Ordinary callbacks which are still queued at that
+    /// point are dropped without running.
+    ///
+    /// Callbacks queued after the final frame's [`Last`](bevy_app::Last) schedule has run cannot
+    /// be run, since the app no longer updates.
+    #[track_caller]
+    pub fn enqueue_main_thread_must_run<Runnable, Output>(
+        &self,
+        runnable: Runnable,
+    ) -> MainThreadJoin<Output>
+    where
+        Runnable: FnOnce(MainThreadContext) -> Output + Send + 'static,
+        Output: Send + 'static,
+    {
+        let (callback, join) = join_callback(
+            self.callback_origin(Location::caller()),
+            IfAbandoned::Run,
+            runnable,
+        );
+        let callback = self
+            .shared
+            .main_thread_tx
+            .shutdown_callbacks
+            .register(callback);
+        if let Err(kind) = self.shared.main_thread_tx.send_callback(callback) {
+            join.origin.fail(kind);
+        }
+        join
+    }
+}
+
+/// Runs the callbacks queued using
+/// [`enqueue_main_thread_must_run`](TaskContext::enqueue_main_thread_must_run) which have not run
+/// yet, followed by the [`on_shutdown`](TokioTasksRuntime::on_shutdown) hooks, on the frame where
+/// the app exits. This is also where the runtime starts
+/// [draining](TokioTasksRuntime::is_draining), and so where tasks learn that the app is exiting,
+/// through [`try_current_tick`](TaskContext::try_current_tick).
+pub(crate) fn run_shutdown_callbacks(
+    world: &mut World,
+    exits: &mut SystemState<EventReader<AppExit>>,
+) {
+    if exits.get_mut(world).read().next().is_none() {
+        return;
+    }
+    let Some(runtime) = world.get_resource::<TokioTasksRuntime>() else {
+        return;
+    };
+    runtime.0.shared.registry.begin_draining();
+    let main_thread_tx = &runtime.0.shared.main_thread_tx;
+    // The copy of each callback in the normal queue is wrapped when it is sent, but these copies
+    // bypass the queue, so they are wrapped here instead.
+    let callbacks: Vec<_> = main_thread_tx
+        .shutdown_callbacks
+        .take_all()
+        .into_iter()
+        .map(|callback| main_thread_tx.wrap(callback))
+        .collect();
+    let shutdown_hooks = runtime.0.shutdown_hooks.clone();
+    let catch_panics = runtime.0.shared.registry.catch_panics();
+    let current_tick = runtime.0.shared.ticks.load(Ordering::SeqCst);
+    for callback in callbacks {
+        crate::panic::run_callback(catch_panics, || {
+            callback(MainThreadContext {
+                world,
+                current_tick,
+            })
+        });
+    }
+    shutdown_hooks.run_all();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use bevy_app::AppExit;
+    use bevy_ecs::system::Resource;
+
+    use crate::{testing, DrainControl, TokioTasksPlugin, TokioTasksRuntime};
+
+    #[derive(Resource, Default)]
+    struct Runs {
+        must_run: usize,
+        ordinary: usize,
+    }
+
+    #[test]
+    fn must_run_callbacks_run_once_on_exit_even_while_paused() {
+        let mut app = testing::app();
+        app.init_resource::<Runs>();
+        app.insert_resource(DrainControl { paused: true });
+        let handle = testing::spawn(&app, |ctx| async move {
+            drop(ctx.enqueue_main_thread_must_run(|ctx| {
+                ctx.world.resource_mut::<Runs>().must_run += 1;
+            }));
+            drop(ctx.enqueue_main_thread(|ctx| {
+                ctx.world.resource_mut::<Runs>().ordinary += 1;
+            }));
+        });
+        testing::poll_tasks(&app);
+        assert!(handle.is_finished());
+        app.update();
+        assert_eq!(app.world().resource::<Runs>().must_run, 0);
+
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        let runs = app.world().resource::<Runs>();
+        assert_eq!((runs.must_run, runs.ordinary), (1, 0));
+
+        app.insert_resource(DrainControl { paused: false });
+        app.update();
+        assert_eq!(app.world().resource::<Runs>().must_run, 1);
+    }
+
+    #[test]
+    fn shutdown_hooks_run_once_on_exit_in_registration_order() {
+        let mut app = testing::app_with(TokioTasksPlugin {
+            catch_panics: true,
+            ..testing::plugin()
+        });
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        for hook in 0..3 {
+            let ran = ran.clone();
+            runtime.on_shutdown(move || ran.lock().unwrap().push(hook));
+        }
+
+        // Caught panics, in a task and in a main thread callback, do not bring the app down.
+        testing::spawn(&app, |_ctx| async move { panic!("test task panic") });
+        testing::spawn(&app, |ctx| async move {
+            drop(ctx.enqueue_main_thread(|_| panic!("test callback panic")));
+        });
+        for _ in 0..3 {
+            testing::poll_tasks(&app);
+            app.update();
+        }
+        let runtime = app.world().resource::<TokioTasksRuntime>();
+        assert_eq!(runtime.health().panicked_tasks, 1);
+        assert!(ran.lock().unwrap().is_empty());
+
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        assert_eq!(*ran.lock().unwrap(), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, Weak};
+
+/// The tasks sleeping in [`sleep_updates`](crate::TaskContext::sleep_updates) when
+/// [`max_sleep_wakeups_per_tick`](crate::TokioTasksPlugin::max_sleep_wakeups_per_tick) is set,
+/// keyed by the tick they want to wake on. Each tick wakes the earliest sleepers which are due,
+/// up to the limit, and leaves the rest for the following ticks.
+///
+/// Only [`UpdateTicks`](crate::UpdateTicks) holds a strong reference, so the sleepers are woken
+/// by the queue being dropped if the main thread shuts down.
+pub(crate) struct SleepWakeups {
+    max_per_tick: usize,
+    sleepers: Mutex<BTreeMap<usize, VecDeque<tokio::sync::oneshot::Sender<()>>>>,
+}
+
+impl SleepWakeups {
+    pub(crate) fn new(max_per_tick: usize) -> Self {
+        Self {
+            // A limit of zero would never wake anyone.
+            max_per_tick: max_per_tick.max(1),
+            sleepers: Mutex::default(),
+        }
+    }
+
+    /// Wakes up to the limit of the sleepers whose target tick is `current_tick` or earlier,
+    /// earliest first. Sleepers which stopped waiting are skipped without counting against the
+    /// limit.
+    pub(crate) fn wake_due(&self, current_tick: usize) {
+        let mut sleepers = self.lock();
+        let mut woken = 0;
+        while woken < self.max_per_tick {
+            let Some(mut due) = sleepers
+                .first_entry()
+                .filter(|due| *due.key() <= current_tick)
+            else {
+                break;
+            };
+            while woken < self.max_per_tick {
+                let Some(sleeper) = due.get_mut().pop_front() else {
+                    break;
+                };
+                if sleeper.send(()).is_ok() {
+                    woken += 1;
+                }
+            }
+            if due.get().is_empty() {
+                due.remove();
+            }
+        }
+    }
+
+    fn lock(
+        &self,
+    ) -> std::sync::MutexGuard<'_, BTreeMap<usize, VecDeque<tokio::sync::oneshot::Sender<()>>>>
+    {
+        self.sleepers
+            .lock()
+            .expect("Sleep wakeups mutex was poisoned")
+    }
+}
+
+/// Waits until `wakeups` wakes this task on or after `target_tick`, or until the main thread
+/// shuts down.
+pub(crate) async fn sleep_until(wakeups: &Weak<SleepWakeups>, target_tick: usize) {
+    let Some(wakeups) = wakeups.upgrade() else {
+        return;
+    };
+    let (wake_tx, wake_rx) = tokio::sync::oneshot::channel();
+    wakeups
+        .lock()
+        .entry(target_tick)
+        .or_default()
+        .push_back(wake_tx);
+    drop(wakeups);
+    // An error means the queue was dropped because the main thread has shut down.
+    let _ = wake_rx.await;
+}
@@ -0,0 +1,92 @@
+use std::future::Future;
+use std::panic::Location;
+use std::sync::Mutex;
+
+use bevy_app::App;
+use bevy_ecs::system::{Res, ResMut, Resource};
+use bevy_utils::tracing;
+
+use crate::{TaskContext, TokioTasksRuntime};
+
+type QueuedStartupTask = Box<dyn FnOnce(&TokioTasksRuntime) + Send>;
+
+/// Tasks queued using [`queue_startup_task`](TokioTasksAppExt::queue_startup_task), waiting to be
+/// spawned by [`spawn_startup_tasks`].
+#[derive(Resource, Default)]
+pub(crate) struct StartupTasks(Mutex<Vec<QueuedStartupTask>>);
+
+/// Extension methods on Bevy's [`App`] for working with the [`TokioTasksRuntime`].
+pub trait TokioTasksAppExt {
+    /// Queue a background task to be spawned on the [`TokioTasksRuntime`] during the `Startup`
+    /// schedule, in the same way as
+    /// [`spawn_background_task`](TokioTasksRuntime::spawn_background_task). Unlike spawning
+    /// through the runtime resource directly, this works from any point of app setup, including
+    /// other plugins' `build`, `finish` and `cleanup` hooks, regardless of whether
+    /// [`TokioTasksPlugin`](crate::TokioTasksPlugin) has been added yet. Queued tasks are spawned
+    /// in the order they were queued.
+    ///
+    /// The tasks are spawned by a system which the plugin adds, so they never run if the plugin is
+    /// never added.
+    fn queue_startup_task<Task, Output, Spawnable>(
+        &mut self,
+        spawnable_task: Spawnable,
+    ) -> &mut Self
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static;
+}
+
+impl TokioTasksAppExt for App {
+    #[track_caller]
+    fn queue_startup_task<Task, Output, Spawnable>(
+        &mut self,
+        spawnable_task: Spawnable,
+    ) -> &mut Self
+    where
+        Task: Future<Output = Output> + Send + 'static,
+        Output: Send + 'static,
+        Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+    {
+        let spawned_at = Location::caller();
+        // The plugin may not have been added yet, in which case it picks up this resource rather
+        // than replacing it.
+        self.init_resource::<StartupTasks>();
+        self.world_mut()
+            .resource_mut::<StartupTasks>()
+            .0
+            .get_mut()
+            .expect("Startup tasks mutex was poisoned")
+            .push(Box::new(move |runtime| {
+                drop(runtime.reserve_task_at(spawned_at).start(spawnable_task));
+            }));
+        self
+    }
+}
+
+/// Spawns every task queued using [`queue_startup_task`](TokioTasksAppExt::queue_startup_task).
+pub(crate) fn spawn_startup_tasks(
+    runtime: Option<Res<TokioTasksRuntime>>,
+    mut startup_tasks: ResMut<StartupTasks>,
+) {
+    let queued = std::mem::take(
+        startup_tasks
+            .0
+            .get_mut()
+            .expect("Startup tasks mutex was poisoned"),
+    );
+    if queued.is_empty() {
+        return;
+    }
+    let Some(runtime) = runtime else {
+        tracing::error!(
+            "{} startup task(s) were queued, but the TokioTasksRuntime resource is missing, so \
+            they will never run",
+            queued.len(),
+        );
+        return;
+    };
+    for spawn in queued {
+        spawn(&runtime);
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+use bevy_utils::tracing::{
+    self,
+    field::{Field, Visit},
+    instrument::Instrumented,
+    span, Event, Instrument, Level, Subscriber,
+};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::registry::TaskRegistry;
+use crate::{TaskId, TaskSlot, TokioTasksRuntime};
+
+/// How many records each task's log buffer keeps. Older records are discarded as new ones arrive.
+const TASK_LOG_CAPACITY: usize = 64;
+
+/// The name of the span which captured tasks run in, and the field of it identifying the buffer
+/// which the task's events go to.
+const TASK_SPAN_NAME: &str = "background_task";
+const CAPTURE_FIELD: &str = "log_capture";
+
+/// The log buffers which tasks have started capturing into, keyed by their capture id, so that
+/// [`TaskLogLayer`] can find a task's buffer when the task's span is created.
+static CAPTURES: Mutex<BTreeMap<u64, Weak<TaskLogBuffer>>> = Mutex::new(BTreeMap::new());
+static NEXT_CAPTURE_ID: AtomicU64 = AtomicU64::new(0);
+
+fn captures() -> MutexGuard<'static, BTreeMap<u64, Weak<TaskLogBuffer>>> {
+    CAPTURES
+        .lock()
+        .expect("Task log captures mutex was poisoned")
+}
+
+/// Whether a task captures its tracing events, which is decided when the task is started.
+#[derive(Clone, Copy)]
+pub(crate) enum LogCapture {
+    Off,
+    /// Only capture if the task has a name, set by
+    /// [`capture_named_task_logs`](crate::TokioTasksPlugin::capture_named_task_logs).
+    Named,
+    /// Set by [`TaskSlot::with_log_capture`].
+    Always,
+}
+
+impl LogCapture {
+    pub(crate) fn for_plugin(capture_named_task_logs: bool) -> Self {
+        if capture_named_task_logs {
+            Self::Named
+        } else {
+            Self::Off
+        }
+    }
+
+    pub(crate) fn applies(self, named: bool) -> bool {
+        match self {
+            Self::Off => false,
+            Self::Named => named,
+            Self::Always => true,
+        }
+    }
+}
+
+/// A tracing event which was emitted by a task capturing its logs, as returned by
+/// [`task_logs`](TokioTasksRuntime::task_logs).
+#[derive(Clone, Debug)]
+pub struct TaskLogRecord {
+    /// The level of the event.
+    pub level: Level,
+    /// The target of the event, which is usually the module path it was emitted from.
+    pub target: &'static str,
+    /// The event's message, followed by its other fields as `key=value` pairs.
+    pub message: String,
+}
+
+/// The bounded buffer which a task's captured events are written to.
+pub(crate) struct TaskLogBuffer {
+    capture_id: u64,
+    records: Mutex<VecDeque<TaskLogRecord>>,
+}
+
+impl TaskLogBuffer {
+    fn new() -> Arc<Self> {
+        let capture_id = NEXT_CAPTURE_ID.fetch_add(1, Ordering::Relaxed);
+        let buffer = Arc::new(Self {
+            capture_id,
+            records: Mutex::new(VecDeque::with_capacity(TASK_LOG_CAPACITY)),
+        });
+        captures().insert(capture_id, Arc::downgrade(&buffer));
+        buffer
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<TaskLogRecord>> {
+        self.records
+            .lock()
+            .expect("Task log buffer mutex was poisoned")
+    }
+
+    fn push(&self, record: TaskLogRecord) {
+        let mut records = self.lock();
+        if records.len() == TASK_LOG_CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// Runs a task's future inside the span which [`TaskLogLayer`] routes into this buffer.
+    pub(crate) fn instrument<Task: Future>(&self, id: TaskId, task: Task) -> Instrumented<Task> {
+        // The span is at the error level so that it is enabled by any filter which lets the
+        // task's events through, whatever their level.
+        task.instrument(tracing::span!(
+            Level::ERROR,
+            TASK_SPAN_NAME,
+            task_id = id.get(),
+            log_capture = self.capture_id
+        ))
+    }
+}
+
+impl Drop for TaskLogBuffer {
+    fn drop(&mut self) {
+        captures().remove(&self.capture_id);
+    }
+}
+
+/// A [`tracing_subscriber`] layer which captures the events of tasks which were started with
+/// [`TaskSlot::with_log_capture`], or which are named while
+/// [`capture_named_task_logs`](crate::TokioTasksPlugin::capture_named_task_logs) is set, into a
+/// bounded buffer per task. The buffers can be read with
+/// [`task_logs`](TokioTasksRuntime::task_logs) and are shown by the debug overlay, which makes it
+/// easy to see what one misbehaving task logged recently without searching the global logs.
+///
+/// Events are captured if they are emitted while the task is being polled, including from any
+/// spans the task enters. Events from the task's main thread callbacks, and from other tasks it
+/// spawns, are not captured. Capturing does not stop the events from also reaching the other
+/// layers of the subscriber.
+///
+/// With Bevy's `LogPlugin`, the layer can be installed through its `custom_layer` field:
+///
+/// ```
+/// # use bevy::log::LogPlugin;
+/// # use bevy_tokio_tasks::TaskLogLayer;
+/// let log_plugin = LogPlugin {
+///     custom_layer: |_| Some(Box::new(TaskLogLayer::default())),
+///     ..LogPlugin::default()
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaskLogLayer {
+    _private: (),
+}
+
+impl<S> Layer<S> for TaskLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let metadata = attrs.metadata();
+        if metadata.name() != TASK_SPAN_NAME || metadata.target() != module_path!() {
+            return;
+        }
+        let mut visitor = CaptureIdVisitor(None);
+        attrs.record(&mut visitor);
+        let Some(buffer) = visitor
+            .0
+            .and_then(|capture_id| captures().get(&capture_id).and_then(Weak::upgrade))
+        else {
+            return;
+        };
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(buffer);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+        for span in scope {
+            let extensions = span.extensions();
+            let Some(buffer) = extensions.get::<Arc<TaskLogBuffer>>() else {
+                continue;
+            };
+            let mut visitor = MessageVisitor::default();
+            event.record(&mut visitor);
+            if !visitor.fields.is_empty() {
+                if !visitor.message.is_empty() {
+                    visitor.message.push(' ');
+                }
+                visitor.message.push_str(&visitor.fields);
+            }
+            buffer.push(TaskLogRecord {
+                level: *event.metadata().level(),
+                target: event.metadata().target(),
+                message: visitor.message,
+            });
+            return;
+        }
+    }
+}
+
+/// Reads the capture id from the fields of a task's span.
+struct CaptureIdVisitor(Option<u64>);
+
+impl Visit for CaptureIdVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == CAPTURE_FIELD {
+            self.0 = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+/// Formats an event's message, and its other fields separately.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+            return;
+        }
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={value:?}", field.name());
+    }
+}
+
+impl TaskRegistry {
+    /// Creates the log buffer for a task which is about to start capturing its logs.
+    pub(crate) fn start_log_capture(&self, id: TaskId) -> Arc<TaskLogBuffer> {
+        let buffer = TaskLogBuffer::new();
+        if let Some(entry) = self.tasks().get_mut(&id) {
+            entry.logs = Some(buffer.clone());
+        }
+        buffer
+    }
+}
+
+impl TaskSlot {
+    /// Makes the task capture the tracing events it emits into a buffer of its most recent
+    /// records, which can be read with [`task_logs`](TokioTasksRuntime::task_logs). Events are
+    /// only captured while a [`TaskLogLayer`] is installed in the global tracing subscriber.
+    pub fn with_log_capture(mut self) -> Self {
+        self.log_capture = LogCapture::Always;
+        self
+    }
+}
+
+impl TokioTasksRuntime {
+    /// Returns the most recent tracing events captured from the given task, oldest first, up to
+    /// 64 records. Returns [`None`] if the task has finished, or was not started with
+    /// [log capture](TaskSlot::with_log_capture).
+    pub fn task_logs(&self, id: TaskId) -> Option<Vec<TaskLogRecord>> {
+        let buffer = self.0.shared.registry.tasks().get(&id)?.logs.clone()?;
+        let records = buffer.lock().iter().cloned().collect();
+        Some(records)
+    }
+}
@@ -0,0 +1,80 @@
+//! Helpers shared by the crate's tests.
+
+use std::future::Future;
+
+use bevy_app::App;
+use tokio::task::JoinHandle;
+
+use crate::{TaskContext, TokioTasksPlugin, TokioTasksRuntime};
+
+/// The most ticks [`run_until_finished`] waits for a task before failing the test.
+const MAX_TICKS: usize = 1000;
+
+/// Returns a plugin whose runtime is a current-thread runtime, so background tasks only make
+/// progress while the tick system runs and tests can reason about ticks exactly.
+pub(crate) fn plugin() -> TokioTasksPlugin {
+    TokioTasksPlugin {
+        make_runtime: Box::new(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to create Tokio runtime for tests")
+        }),
+        ..TokioTasksPlugin::default()
+    }
+}
+
+/// Returns an app with the given plugin added.
+pub(crate) fn app_with(plugin: TokioTasksPlugin) -> App {
+    let mut app = App::new();
+    app.add_plugins(plugin);
+    app
+}
+
+/// Returns an app with the default test [`plugin`] added.
+pub(crate) fn app() -> App {
+    app_with(plugin())
+}
+
+/// Spawns a background task onto the app's runtime.
+pub(crate) fn spawn<Task, Output, Spawnable>(
+    app: &App,
+    spawnable_task: Spawnable,
+) -> JoinHandle<Output>
+where
+    Task: Future<Output = Output> + Send + 'static,
+    Output: Send + 'static,
+    Spawnable: FnOnce(TaskContext) -> Task + Send + 'static,
+{
+    app.world()
+        .resource::<TokioTasksRuntime>()
+        .spawn_background_task(spawnable_task)
+}
+
+/// Returns the context of a task which has already finished, for queueing main thread work and
+/// reading the tick count directly from a test.
+pub(crate) fn context(app: &mut App) -> TaskContext {
+    let handle = spawn(app, |ctx| async move { ctx });
+    run_until_finished(app, handle)
+}
+
+/// Gives the app's current-thread runtime a chance to run its tasks without advancing the tick.
+pub(crate) fn poll_tasks(app: &App) {
+    let runtime = app.world().resource::<TokioTasksRuntime>().runtime();
+    for _ in 0..8 {
+        runtime.block_on(tokio::task::yield_now());
+    }
+}
+
+/// Updates the app until the task has finished, and returns its output.
+pub(crate) fn run_until_finished<Output>(app: &mut App, handle: JoinHandle<Output>) -> Output {
+    for _ in 0..MAX_TICKS {
+        poll_tasks(app);
+        if handle.is_finished() {
+            let runtime = app.world().resource::<TokioTasksRuntime>().runtime();
+            return runtime.block_on(handle).expect("Background task failed");
+        }
+        app.update();
+    }
+    panic!("Background task did not finish within {MAX_TICKS} ticks");
+}
@@ -0,0 +1,132 @@
+use tokio::sync::broadcast;
+
+use crate::TaskContext;
+
+/// An item yielded by a [`LosslessTickStream`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LosslessTick {
+    /// An update tick occurred, and this is its [tick number](TaskContext::current_tick).
+    Tick(usize),
+    /// The stream fell so far behind that this many ticks were discarded before it could receive
+    /// them. The stream continues with the oldest tick which is still buffered.
+    Lagged(u64),
+}
+
+/// Yields every update tick exactly once, in order, unlike the tick waiting helpers which only
+/// observe the latest tick. Created by
+/// [`lossless_tick_stream`](TaskContext::lossless_tick_stream).
+///
+/// Ticks are buffered until the stream receives them, up to the plugin's
+/// [`lossless_tick_capacity`](crate::TokioTasksPlugin::lossless_tick_capacity). If the task
+/// falls further behind than that, the oldest ticks are discarded, and the stream reports how many
+/// with a [`LosslessTick::Lagged`] before carrying on from the oldest tick it still has. Ticks are
+/// never delayed to wait for a slow stream.
+pub struct LosslessTickStream {
+    tick_rx: broadcast::Receiver<usize>,
+}
+
+impl LosslessTickStream {
+    /// Waits for the next tick and returns it, or returns a [`LosslessTick::Lagged`] if ticks
+    /// were discarded since the previous call. Returns [`None`] once the main thread has shut
+    /// down and every buffered tick has been received.
+    pub async fn next(&mut self) -> Option<LosslessTick> {
+        match self.tick_rx.recv().await {
+            Ok(tick) => Some(LosslessTick::Tick(tick)),
+            Err(broadcast::error::RecvError::Lagged(missed)) => Some(LosslessTick::Lagged(missed)),
+            Err(broadcast::error::RecvError::Closed) => None,
+        }
+    }
+}
+
+impl TaskContext {
+    /// Returns a [`LosslessTickStream`] which yields every update tick after this is called,
+    /// for tasks which must process each tick exactly once, such as ones recording per-tick
+    /// snapshots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`lossless_tick_capacity`](crate::TokioTasksPlugin::lossless_tick_capacity) is
+    /// not set.
+    pub fn lossless_tick_stream(&self) -> LosslessTickStream {
+        let tick_rx = self
+            .shared
+            .lossless_tick_rx
+            .as_ref()
+            .expect(
+                "Lossless tick streams require TokioTasksPlugin::lossless_tick_capacity to be set",
+            )
+            .resubscribe();
+        LosslessTickStream { tick_rx }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::App;
+
+    use super::{LosslessTick, LosslessTickStream};
+    use crate::{testing, TokioTasksPlugin, TokioTasksRuntime};
+
+    fn app_with_capacity(capacity: usize) -> App {
+        testing::app_with(TokioTasksPlugin {
+            lossless_tick_capacity: Some(capacity),
+            ..testing::plugin()
+        })
+    }
+
+    /// Returns a stream along with the tick it was created on.
+    fn stream(app: &mut App) -> (LosslessTickStream, usize) {
+        let ctx = testing::context(app);
+        (ctx.lossless_tick_stream(), ctx.current_tick())
+    }
+
+    fn next(app: &App, stream: &mut LosslessTickStream) -> Option<LosslessTick> {
+        let runtime = app.world().resource::<TokioTasksRuntime>().runtime();
+        runtime.block_on(stream.next())
+    }
+
+    #[test]
+    fn every_tick_is_yielded_in_order() {
+        let mut app = app_with_capacity(8);
+        let (mut stream, start) = stream(&mut app);
+        for _ in 0..3 {
+            app.update();
+        }
+        for offset in 1..=3 {
+            assert_eq!(
+                next(&app, &mut stream),
+                Some(LosslessTick::Tick(start + offset))
+            );
+        }
+    }
+
+    #[test]
+    fn streams_which_fall_behind_report_the_discarded_ticks() {
+        let mut app = app_with_capacity(2);
+        let (mut stream, start) = stream(&mut app);
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(next(&app, &mut stream), Some(LosslessTick::Lagged(3)));
+        assert_eq!(next(&app, &mut stream), Some(LosslessTick::Tick(start + 4)));
+        assert_eq!(next(&app, &mut stream), Some(LosslessTick::Tick(start + 5)));
+    }
+
+    #[test]
+    fn streams_end_once_the_app_shuts_down() {
+        let mut app = app_with_capacity(8);
+        let (mut stream, start) = stream(&mut app);
+        app.update();
+        let runtime = app
+            .world_mut()
+            .remove_resource::<TokioTasksRuntime>()
+            .expect("The runtime resource is missing");
+        drop(app);
+        let runtime = runtime.runtime();
+        assert_eq!(
+            runtime.block_on(stream.next()),
+            Some(LosslessTick::Tick(start + 1))
+        );
+        assert_eq!(runtime.block_on(stream.next()), None);
+    }
+}